@@ -0,0 +1,141 @@
+// Self-play match between two engine binaries speaking UCI, used to check
+// that a search change gains strength. Each pair of games starts from the
+// same random opening, with both engines taking Red once.
+//
+//   cargo run --release --example selfplay -- <new engine> <old engine> [pairs] [movetime ms] [seed]
+//
+// scripts/selfplay.sh builds the two engines from git revisions and runs it.
+use chess_engine::{generate_legal_moves, has_legal_move, Board, Move};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+const START: &str = "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR w - - 0 1";
+const OPENING_PLIES: usize = 4; // random moves before the engines take over
+const MAX_PLIES: usize = 300; // longer games are scored as draws
+
+struct Engine {
+    _child: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+}
+
+impl Engine {
+    fn new(path: &str) -> Engine {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap_or_else(|e| panic!("cannot start {}: {}", path, e));
+        let input = child.stdin.take().unwrap();
+        let output = BufReader::new(child.stdout.take().unwrap());
+        Engine { _child: child, input, output }
+    }
+
+    // Older builds ignore `movetime` and think for their built-in time
+    fn best_move(&mut self, board: &Board, movetime_ms: u64) -> Option<Move> {
+        writeln!(self.input, "position fen {}", board.to_fen()).unwrap();
+        writeln!(self.input, "go movetime {}", movetime_ms).unwrap();
+        self.input.flush().unwrap();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.output.read_line(&mut line).unwrap() == 0 {
+                return None;
+            }
+            if let Some(mv) = line.trim().strip_prefix("bestmove ") {
+                return Move::from_uci(mv.split_whitespace().next()?);
+            }
+        }
+    }
+}
+
+fn legal(board: &Board) -> Vec<Move> {
+    generate_legal_moves(board)
+        .into_iter()
+        .filter(|mv| board.clone().make_move(mv.from, mv.to))
+        .collect()
+}
+
+// Red's score: 1 for a win, 0.5 for a draw. A side without a legal move has
+// lost; an illegal or missing move loses too. Threefold repetition or
+// MAX_PLIES is a draw.
+fn play(red: &mut Engine, black: &mut Engine, opening: &[Move], movetime_ms: u64) -> f64 {
+    let mut board = Board::from_fen(START).unwrap();
+    for mv in opening {
+        board.make_move(mv.from, mv.to);
+    }
+    let mut seen = Vec::new();
+    for _ in 0..MAX_PLIES {
+        let red_loses = if board.red_to_move { 0.0 } else { 1.0 };
+        if !has_legal_move(&board) {
+            return red_loses;
+        }
+        let fen = board.to_fen();
+        let key: Vec<&str> = fen.split(' ').take(2).collect();
+        let key = key.join(" ");
+        if seen.iter().filter(|k| **k == key).count() >= 2 {
+            return 0.5;
+        }
+        seen.push(key);
+
+        let engine = if board.red_to_move { &mut *red } else { &mut *black };
+        let played = match engine.best_move(&board, movetime_ms) {
+            Some(mv) => legal(&board).contains(&mv) && board.make_move(mv.from, mv.to),
+            None => false,
+        };
+        if !played {
+            return red_loses;
+        }
+    }
+    0.5
+}
+
+fn elo(score: f64) -> f64 {
+    -400.0 * (1.0 / score.clamp(0.001, 0.999) - 1.0).log10()
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() < 2 {
+        eprintln!("Usage: selfplay <new engine> <old engine> [pairs] [movetime ms] [seed]");
+        std::process::exit(1);
+    }
+    let number = |i: usize, default: u64| args.get(i).map_or(default, |v| v.parse().expect("a number"));
+    let (pairs, movetime_ms, seed) = (number(2, 40), number(3, 200), number(4, 2026));
+
+    let mut new = Engine::new(&args[0]);
+    let mut old = Engine::new(&args[1]);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut scores = Vec::new();
+    for pair in 0..pairs {
+        let mut board = Board::from_fen(START).unwrap();
+        let mut opening = Vec::new();
+        for _ in 0..OPENING_PLIES {
+            let mv = legal(&board).choose(&mut rng).unwrap().clone();
+            board.make_move(mv.from, mv.to);
+            opening.push(mv);
+        }
+        scores.push(play(&mut new, &mut old, &opening, movetime_ms));
+        scores.push(1.0 - play(&mut old, &mut new, &opening, movetime_ms));
+
+        let count = |value: f64| scores.iter().filter(|&&s| s == value).count();
+        println!("after {} pairs: +{} ={} -{}", pair + 1, count(1.0), count(0.5), count(0.0));
+    }
+
+    // 95% interval from the spread of the single game scores
+    let n = scores.len() as f64;
+    let mean = scores.iter().sum::<f64>() / n;
+    let variance = scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n;
+    let margin = 1.96 * (variance / n).sqrt();
+    println!(
+        "score {:.1}%, {:+.0} Elo (95% interval {:+.0} to {:+.0})",
+        mean * 100.0,
+        elo(mean),
+        elo(mean - margin),
+        elo(mean + margin)
+    );
+}
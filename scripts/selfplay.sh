@@ -0,0 +1,38 @@
+#!/bin/sh
+# Builds the engine at two git revisions and plays them against each other
+# with examples/selfplay.rs:
+#
+#   scripts/selfplay.sh <new rev> <old rev> [pairs] [movetime ms] [seed]
+#
+# Builds from before `go movetime` was understood think for a fixed 1000 ms
+# per move, so that constant is patched to the requested movetime as well.
+# Results vary somewhat between runs and machines since the engines play on
+# time; the PVS/LMR commit was measured against the baseline with the
+# defaults below (40 pairs, 200 ms, seed 2026).
+set -e
+
+if [ $# -lt 2 ]; then
+    echo "Usage: $0 <new rev> <old rev> [pairs] [movetime ms] [seed]" >&2
+    exit 1
+fi
+new_rev=$1
+old_rev=$2
+pairs=${3:-40}
+movetime=${4:-200}
+seed=${5:-2026}
+
+root=$(git rev-parse --show-toplevel)
+work=$(mktemp -d)
+trap 'git -C "$root" worktree remove --force "$work/new" 2>/dev/null; git -C "$root" worktree remove --force "$work/old" 2>/dev/null; rm -rf "$work"' EXIT
+
+for side in new old; do
+    eval rev=\$${side}_rev
+    git -C "$root" worktree add --quiet --detach "$work/$side" "$rev"
+    sed -i "s/SearchInfo::new(1000)/SearchInfo::new($movetime)/; s/let time_limit = 1000;/let time_limit = $movetime;/" \
+        "$work/$side/src/search.rs"
+    cargo build --release --quiet --manifest-path "$work/$side/Cargo.toml" --target-dir "$work/target-$side"
+done
+
+cd "$root"
+cargo run --release --quiet --example selfplay -- \
+    "$work/target-new/release/chess_engine" "$work/target-old/release/chess_engine" "$pairs" "$movetime" "$seed"
@@ -0,0 +1,98 @@
+// Attack maps: for every square, which kinds of piece of each side could
+// capture there, and from which squares. They are built in one pass over
+// the board with the move generator's attack rules, and the evaluation reads
+// them to tell safe squares from ones that a cheaper enemy piece guards, and
+// to find the pieces hitting a palace.
+use crate::board::{Board, Color, Piece};
+use crate::moves::piece_attacks;
+
+pub struct AttackMaps {
+    kinds: [[[u8; 9]; 10]; 2],     // per side and square, a bit per attacking kind
+    origins: [[[u128; 9]; 10]; 2], // per side and square, a bit per attacker's square
+}
+
+impl AttackMaps {
+    pub fn new(board: &Board) -> Self {
+        let mut kinds = [[[0; 9]; 10]; 2];
+        let mut origins = [[[0; 9]; 10]; 2];
+        for rank in 0..10 {
+            for file in 0..9 {
+                if let Some((color, piece)) = board.squares[rank][file].piece {
+                    let (map, from) = (&mut kinds[side_index(color)], &mut origins[side_index(color)]);
+                    piece_attacks(board, (rank, file), |(r, f)| {
+                        map[r][f] |= piece_bit(piece);
+                        from[r][f] |= square_bit((rank, file));
+                    });
+                }
+            }
+        }
+        AttackMaps { kinds, origins }
+    }
+
+    // The squares of the pieces of `color` attacking `pos`, a bit each as
+    // given by square_bit
+    pub fn attackers(&self, pos: (usize, usize), color: Color) -> u128 {
+        self.origins[side_index(color)][pos.0][pos.1]
+    }
+
+    pub fn is_attacked(&self, pos: (usize, usize), color: Color) -> bool {
+        self.kinds[side_index(color)][pos.0][pos.1] != 0
+    }
+
+    pub fn is_attacked_by(&self, pos: (usize, usize), color: Color, piece: Piece) -> bool {
+        self.kinds[side_index(color)][pos.0][pos.1] & piece_bit(piece) != 0
+    }
+
+    // Whether `color` attacks the square with a piece worth less than `piece`,
+    // which would make it a bad square for `piece` to stand on
+    pub fn is_attacked_by_cheaper(&self, pos: (usize, usize), color: Color, piece: Piece) -> bool {
+        let cheaper = ALL_PIECES
+            .iter()
+            .filter(|&&other| piece_class(other) < piece_class(piece))
+            .fold(0, |mask, &other| mask | piece_bit(other));
+        self.kinds[side_index(color)][pos.0][pos.1] & cheaper != 0
+    }
+}
+
+const ALL_PIECES: [Piece; 7] = [
+    Piece::Soldier,
+    Piece::Advisor,
+    Piece::Elephant,
+    Piece::Horse,
+    Piece::Cannon,
+    Piece::Chariot,
+    Piece::General,
+];
+
+fn side_index(color: Color) -> usize {
+    match color {
+        Color::Red => 0,
+        Color::Black => 1,
+    }
+}
+
+fn piece_bit(piece: Piece) -> u8 {
+    1 << ALL_PIECES.iter().position(|&other| other == piece).unwrap()
+}
+
+// The bit of a square in the masks returned by AttackMaps::attackers
+pub fn square_bit((rank, file): (usize, usize)) -> u128 {
+    1 << (rank * 9 + file)
+}
+
+// The square of the lowest bit set in a mask of square bits
+pub fn first_square(mask: u128) -> (usize, usize) {
+    let index = mask.trailing_zeros() as usize;
+    (index / 9, index % 9)
+}
+
+// Rough worth, for deciding which exchanges lose material
+fn piece_class(piece: Piece) -> u8 {
+    match piece {
+        Piece::Soldier => 0,
+        Piece::Advisor | Piece::Elephant => 1,
+        Piece::Horse | Piece::Cannon => 2,
+        Piece::Chariot => 3,
+        Piece::General => 4,
+    }
+}
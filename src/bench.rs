@@ -0,0 +1,108 @@
+use std::time::Instant;
+use chess_engine::{Board, SearchLimits, Searcher};
+use crate::log::{diag, send};
+
+pub const DEFAULT_BENCH_DEPTH: i32 = 5;
+pub const DEFAULT_BENCH_THREADS: usize = 1;
+pub const DEFAULT_BENCH_HASH_MB: usize = 16;
+
+// Fixed suite for `bench`: the initial position, openings, middlegames and
+// endgames. Changing this list changes every bench signature.
+const BENCH_POSITIONS: [&str; 50] = [
+    "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR w - - 0 1",
+    "2bakabnr/3r5/9/p1p1p3p/2P3p2/6Pc1/P5c1P/4B1NC1/4A4/RN1K1AB1R w - - 6 9",
+    "2b1kab1r/4a4/9/p1P1p3p/7C1/6pc1/P5c1P/4B1N2/4A4/3rKAB1R w - - 0 15",
+    "4kab2/4a4/4b4/pP2p3p/2rc5/6B2/P2c4P/3A2N2/1C7/4KABR1 w - - 11 23",
+    "4kab2/4a4/4b4/pP2p3p/9/6B1c/P8/1N3K2B/9/5A3 w - - 0 33",
+    "5Pb2/4k4/9/8p/9/1p7/P8/5K2B/c8/1N3AB2 w - - 1 46",
+    "6P2/9/5k3/8p/5c3/P8/9/4B4/5K3/9 w - - 2 61",
+    "1rbakab1r/9/7cn/p1p1p1p1p/9/9/P1P1P1PCP/1c2B4/3N5/R2AKABNR b - - 5 5",
+    "r1bk1abr1/4c4/1c4n2/pC4p1p/4p4/9/P1P1P1P1P/4B4/4K4/RN1A1ABNR w - - 5 9",
+    "r1b1kab2/9/1c4n2/p1c3p1p/4p4/2P6/P3P1PrP/2N1B4/1C2A4/2R1KABNR w - - 17 15",
+    "r3kab2/9/1P2b1n2/p1R3p1p/4p4/9/P3P1r1P/1c2B1N2/1C2A4/4KAB1R w - - 4 23",
+    "3rk4/4a4/1P2b1n1b/p5p1p/4p4/P8/1R2P3r/4B4/2c1A3N/2C1KAB1R w - - 0 33",
+    "1rbakabnr/9/4c2c1/2p1p1pCp/p8/9/P1P1P1P1P/4B4/4A4/RN2KABNR b - - 5 5",
+    "2baka1nr/9/4b4/p1p1p1p1p/9/9/R1P3P1P/4C1N2/4A4/1rB1KA2R w - - 1 9",
+    "2Rak1rn1/4a4/4b4/4p1p1p/9/9/2P3P1P/4C4/4N4/r2AKA1R1 w - - 3 15",
+    "4ka1RC/9/3a5/4p1n2/6p1p/9/2P3P1P/9/4N4/3AKA3 w - - 5 23",
+    "4kaR1C/8n/3a5/9/4p1p2/8p/2P3P1P/9/4N4/3AKA3 w - - 25 33",
+    "4ka3/9/3a5/4C4/4R4/4p4/2P3p1P/9/4N4/3AKA3 w - - 16 46",
+    "1rbakabnr/9/9/p1p1p1p1p/3C5/7c1/P1P1P1P1P/1c5CB/9/RNBAKA1NR b - - 6 5",
+    "1rbakabnr/9/4c4/p1p1p1p1p/9/PCP6/4P1P1P/N7c/4A4/R1B1KAB1R w - - 0 9",
+    "2bakab1r/9/6n2/p3p1p1p/P1p6/8C/crN3P1P/5A2R/9/R1B1KAB2 w - - 6 15",
+    "3akab1C/8n/4b4/P3p1p1p/2p6/9/3c2r1P/3R5/R3A4/2B1KAB2 w - - 10 23",
+    "3ak1b2/9/4b4/P3p1R1p/2p6/9/9/4B2n1/4A4/4KAB2 w - - 1 33",
+    "3a2b2/4k4/4b4/P7R/9/2p1n4/9/5A3/9/3K1AB2 w - - 17 46",
+    "3a5/4k4/4b4/9/9/9/3n5/3KB4/4A4/9 w - - 2 61",
+    "2b2kbCr/r8/7c1/p1p1p1p1p/1c7/9/P1P1P1P1P/9/9/RNBAKABNR b - - 0 5",
+    "1rbakabnr/9/7c1/p1p1p1p1p/1c7/9/P1P1P1P1P/B6C1/4A4/RN2KABNR b - - 3 4",
+    "1rbakabnr/9/3c5/p3p1p1p/2p5c/6P2/P1P1P3P/B3B2CN/4A4/RN2KA2R w - - 10 7",
+    "3akabnr/9/3cb4/p3p1pCp/2p6/6P1P/P1r1P4/B3B3R/3NA4/R3KA3 b - - 1 12",
+    "1R1R1abnr/4k4/4b4/p3r1p1p/2p6/6P1P/P8/B8/3cA4/4KAB2 w - - 1 18",
+    "1rbakabnr/9/4c2c1/p1p1p1p1p/9/9/P1P1P1P1P/4B2C1/4A4/RN2KABNR b - - 3 4",
+    "2bakabnr/9/7c1/R1p1p1p1p/9/9/2P3P1P/4B1NC1/4A4/1r2KAB1R w - - 0 7",
+    "1rb1kabr1/4a4/9/6R1p/7c1/9/2P3P1P/4B1N2/9/3AKAB1R b - - 0 12",
+    "3akab2/9/9/2R5p/1r7/9/2P3P1r/4B4/9/3AKAB1c w - - 0 18",
+    "rCbakabnr/9/1c7/p1p1p1p1p/9/9/P1P1c1P1P/1CN6/9/R1BAKABNR b - - 1 4",
+    "r1bakabn1/1C6r/9/p1p1p1p1p/9/9/P1P3cR1/1CN6/9/R1BAKABN1 w - - 0 7",
+    "1rba1abn1/1C2k1r2/9/p1p3p1p/4p4/9/P1P1R4/2N6/9/R1BAKABN1 b - - 4 12",
+    "3a1abn1/4k4/4b4/p1p3p1p/4R4/9/P1P1N4/6Nr1/1r2A4/R1B1KAB2 w - - 11 18",
+    "3ak4/4a4/6n2/p1p3p1p/9/9/P1P3r2/7R1/9/1RBAKA3 w - - 4 26",
+    "3aka3/9/9/p1p5R/8p/9/1r7/4B4/4A4/1R1A1K3 b - - 7 36",
+    "3aka3/9/9/9/8p/R8/9/9/5Kr2/3A1A3 w - - 15 48",
+    "9/1R2k4/9/9/9/6p2/9/5K3/4A4/5A3 b - - 16 62",
+    "1rbakabnr/9/4c2c1/p1p1p1p1p/9/9/P1P1P1P1P/4B2C1/3N5/R2AKABNR b - - 3 4",
+    "2b1kabnr/4a4/4c3c/p1p1C1p1p/9/9/P1P1P1P1P/4B1N2/3r5/R2AKAB1R w - - 1 7",
+    "C3kabnr/4a4/b3c4/2p3p1p/9/6P2/P1P1P2c1/4B4/8N/R2AKAB2 b - - 4 12",
+    "C3kabnr/4a4/4c4/2R3p1p/2c6/6P2/P3P4/4B4/8N/3AKAB2 w - - 1 18",
+    "C3kabnr/4a4/4c4/6p1p/c1R6/6P2/P3P2N1/4B4/4K4/3A1AB2 w - - 17 26",
+    "3ak4/4a4/9/9/9/9/9/4N4/9/4K4 w - - 0 1",
+    "3k5/4a4/4ba3/9/9/6n2/9/9/4R4/5K3 w - - 0 1",
+    "4ka3/9/2b6/9/9/2P6/9/4C4/9/3K5 w - - 0 1",
+];
+
+// bench [depth] [threads] [hash]
+pub fn parse_bench_args(args: &[String]) -> (i32, usize, usize) {
+    let depth = args.first().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_BENCH_DEPTH);
+    let threads = args.get(1).and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_BENCH_THREADS);
+    let hash_mb = args.get(2).and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_BENCH_HASH_MB);
+    (depth.max(1), threads.max(1), hash_mb.max(1))
+}
+
+// Search every suite position to a fixed depth from an empty table and
+// report the node total. With one thread the total is the bench signature:
+// it only changes when the search or evaluation behaves differently. Inside
+// the UCI loop the report is diagnostic output, so stdout stays protocol-only.
+pub fn run_bench(depth: i32, threads: usize, hash_mb: usize, under_uci: bool) -> u64 {
+    let report = |line: String| {
+        if under_uci {
+            diag!("{}", line);
+        } else {
+            send!("{}", line);
+        }
+    };
+    let searcher = Searcher::new(hash_mb, threads);
+    let limits = SearchLimits {
+        depth: Some(depth),
+        ..Default::default()
+    };
+
+    let start = Instant::now();
+    let mut total_nodes = 0;
+    for (i, fen) in BENCH_POSITIONS.iter().enumerate() {
+        let board = Board::from_fen(fen).expect("bench positions are valid FENs");
+        report(format!("Position {}/{}: {}", i + 1, BENCH_POSITIONS.len(), fen));
+        searcher.clear();
+        total_nodes += searcher.search(&board, &limits, &mut ()).nodes;
+    }
+    let elapsed_ms = start.elapsed().as_millis().max(1) as u64;
+
+    report("===========================".to_string());
+    report(format!("Depth           : {}", depth));
+    report(format!("Threads         : {}", threads));
+    report(format!("Hash (MB)       : {}", hash_mb));
+    report(format!("Total time (ms) : {}", elapsed_ms));
+    report(format!("Nodes searched  : {}", total_nodes));
+    report(format!("Nodes/second    : {}", total_nodes * 1000 / elapsed_ms));
+    total_nodes
+}
@@ -32,6 +32,12 @@ pub struct Board {
     pub fullmove_number: u16,
 }
 
+impl Default for Board {
+    fn default() -> Self {
+        Board::new()
+    }
+}
+
 impl Board {
     pub fn new() -> Self {
         Board {
@@ -65,7 +71,7 @@ impl Board {
                         rank_idx + 1
                     ));
                 }
-                if c.is_digit(10) {
+                if c.is_ascii_digit() {
                     let empty_squares = c.to_digit(10).unwrap() as usize;
                     file_idx += empty_squares;
                 } else {
@@ -122,12 +128,71 @@ impl Board {
         Ok(board)
     }
 
+    pub fn to_fen(&self) -> String {
+        let mut ranks = Vec::with_capacity(10);
+        for rank in 0..10 {
+            let mut rank_str = String::new();
+            let mut empty = 0;
+            for file in 0..9 {
+                match self.squares[rank][file].piece {
+                    Some((color, piece)) => {
+                        if empty > 0 {
+                            rank_str.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        rank_str.push(piece_letter(color, piece));
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                rank_str.push_str(&empty.to_string());
+            }
+            ranks.push(rank_str);
+        }
+
+        format!(
+            "{} {} - - {} {}",
+            ranks.join("/"),
+            if self.red_to_move { "w" } else { "b" },
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
+    // The position seen in a mirror: file a becomes file i and so on. The
+    // rules are symmetric, so evaluation and search results are unchanged.
+    pub fn mirror_files(&self) -> Board {
+        let mut mirrored = self.clone();
+        for rank in mirrored.squares.iter_mut() {
+            rank.reverse();
+        }
+        mirrored
+    }
+
+    // The same position with the colors exchanged: every piece changes color
+    // and moves to the opposite rank, and the other side is to move. Files
+    // keep their letters, so Red's a-file chariot becomes Black's.
+    pub fn flip_colors(&self) -> Board {
+        let mut flipped = self.clone();
+        for rank in 0..10 {
+            for file in 0..9 {
+                flipped.squares[rank][file].piece = self.squares[9 - rank][file]
+                    .piece
+                    .map(|(color, piece)| (color.opponent(), piece));
+            }
+        }
+        flipped.red_to_move = !self.red_to_move;
+        flipped
+    }
+
     pub fn setup_initial_position(&mut self) {
         // clear the board
         self.squares = [[Square { piece: None }; 9]; 10];
         
-        // set up red pieces (bottom side)
-        // back rank (rank 9)
+        // set up red pieces (bottom side); rows are squares[] indices, rank
+        // digits are ICCS, so row r is rank 9 - r
+        // back rank (row 9, rank 0)
         self.squares[9][0].piece = Some((Color::Red, Piece::Chariot));
         self.squares[9][1].piece = Some((Color::Red, Piece::Horse));
         self.squares[9][2].piece = Some((Color::Red, Piece::Elephant));
@@ -138,19 +203,19 @@ impl Board {
         self.squares[9][7].piece = Some((Color::Red, Piece::Horse));
         self.squares[9][8].piece = Some((Color::Red, Piece::Chariot));
         
-        // cannons (rank 7)
+        // cannons (row 7, rank 2)
         self.squares[7][1].piece = Some((Color::Red, Piece::Cannon));
         self.squares[7][7].piece = Some((Color::Red, Piece::Cannon));
         
-        // soldiers (rank 3)
-        self.squares[3][0].piece = Some((Color::Red, Piece::Soldier));
-        self.squares[3][2].piece = Some((Color::Red, Piece::Soldier));
-        self.squares[3][4].piece = Some((Color::Red, Piece::Soldier));
-        self.squares[3][6].piece = Some((Color::Red, Piece::Soldier));
-        self.squares[3][8].piece = Some((Color::Red, Piece::Soldier));
+        // soldiers (row 6, rank 3)
+        self.squares[6][0].piece = Some((Color::Red, Piece::Soldier));
+        self.squares[6][2].piece = Some((Color::Red, Piece::Soldier));
+        self.squares[6][4].piece = Some((Color::Red, Piece::Soldier));
+        self.squares[6][6].piece = Some((Color::Red, Piece::Soldier));
+        self.squares[6][8].piece = Some((Color::Red, Piece::Soldier));
 
         // set up black pieces (top side)
-        // back rank (rank 0)
+        // back rank (row 0, rank 9)
         self.squares[0][0].piece = Some((Color::Black, Piece::Chariot));
         self.squares[0][1].piece = Some((Color::Black, Piece::Horse));
         self.squares[0][2].piece = Some((Color::Black, Piece::Elephant));
@@ -161,16 +226,16 @@ impl Board {
         self.squares[0][7].piece = Some((Color::Black, Piece::Horse));
         self.squares[0][8].piece = Some((Color::Black, Piece::Chariot));
         
-        // cannons (rank 2)
+        // cannons (row 2, rank 7)
         self.squares[2][1].piece = Some((Color::Black, Piece::Cannon));
         self.squares[2][7].piece = Some((Color::Black, Piece::Cannon));
         
-        // soldiers (rank 6)
-        self.squares[6][0].piece = Some((Color::Black, Piece::Soldier));
-        self.squares[6][2].piece = Some((Color::Black, Piece::Soldier));
-        self.squares[6][4].piece = Some((Color::Black, Piece::Soldier));
-        self.squares[6][6].piece = Some((Color::Black, Piece::Soldier));
-        self.squares[6][8].piece = Some((Color::Black, Piece::Soldier));
+        // soldiers (row 3, rank 6)
+        self.squares[3][0].piece = Some((Color::Black, Piece::Soldier));
+        self.squares[3][2].piece = Some((Color::Black, Piece::Soldier));
+        self.squares[3][4].piece = Some((Color::Black, Piece::Soldier));
+        self.squares[3][6].piece = Some((Color::Black, Piece::Soldier));
+        self.squares[3][8].piece = Some((Color::Black, Piece::Soldier));
 
         // red moves first
         self.red_to_move = true;
@@ -256,7 +321,8 @@ impl Board {
 
         // Update game state
         self.red_to_move = !self.red_to_move;
-        if !self.red_to_move {
+        // a full move is complete once Black has replied
+        if self.red_to_move {
             self.fullmove_number += 1;
         }
 
@@ -271,42 +337,173 @@ impl Board {
 
     // Check if a side is in check
     pub fn is_in_check(&self, color: Color) -> bool {
-        // Find the general's position
-        let mut general_pos = None;
+        match self.find_general(color) {
+            Some(general_pos) => self.is_square_attacked(general_pos, color.opponent()),
+            None => false,
+        }
+    }
+
+    pub fn find_general(&self, color: Color) -> Option<(usize, usize)> {
         for rank in 0..10 {
-            for file in 0..9 {
-                if let Some((piece_color, Piece::General)) = self.squares[rank][file].piece {
-                    if piece_color == color {
-                        general_pos = Some((rank, file));
-                        break;
-                    }
+            for file in 3..6 {
+                if self.squares[rank][file].piece == Some((color, Piece::General)) {
+                    return Some((rank, file));
                 }
             }
         }
+        None
+    }
+
+    pub fn is_square_attacked(&self, target: (usize, usize), color: Color) -> bool {
+        !self.attackers(target, color).is_empty()
+    }
 
-        if let Some(general_pos) = general_pos {
-            // Check if any opponent's piece can capture the general
-            let opponent_color = if color == Color::Red {
-                Color::Black
-            } else {
-                Color::Red
-            };
-            for rank in 0..10 {
-                for file in 0..9 {
-                    if let Some((piece_color, _)) = self.squares[rank][file].piece {
-                        if piece_color == opponent_color {
-                            let moves = generate_piece_moves(self, (rank, file));
-                            for mv in moves {
-                                if mv == general_pos {
-                                    return true;
-                                }
+    // Squares of all `color` pieces that could capture on `target` in the
+    // current occupancy. Cannon screens, horse legs and elephant eyes are all
+    // read from the board, so callers can remove pieces and ask again.
+    pub fn attackers(&self, target: (usize, usize), color: Color) -> Vec<(usize, usize)> {
+        let mut attackers = Vec::new();
+        let (rank, file) = (target.0 as i32, target.1 as i32);
+        let target_piece = self.squares[target.0][target.1].piece;
+
+        // Chariots, cannons and the facing general along ranks and files
+        for (dr, df) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let mut r = rank + dr;
+            let mut f = file + df;
+            let mut screened = false;
+            while let Some(square) = self.square_at(r, f) {
+                if let Some((piece_color, piece)) = square.piece {
+                    if !screened {
+                        if piece_color == color {
+                            let facing_general = piece == Piece::General
+                                && df == 0
+                                && target_piece == Some((color.opponent(), Piece::General));
+                            if piece == Piece::Chariot || facing_general {
+                                attackers.push((r as usize, f as usize));
                             }
                         }
+                        screened = true;
+                    } else {
+                        if piece_color == color && piece == Piece::Cannon {
+                            attackers.push((r as usize, f as usize));
+                        }
+                        break;
                     }
                 }
+                r += dr;
+                f += df;
             }
         }
-        false
+
+        // Horses: the leg is the square next to the horse, towards the target
+        for (dr, df) in [(2, 1), (2, -1), (-2, 1), (-2, -1), (1, 2), (1, -2), (-1, 2), (-1, -2)] {
+            let (hr, hf) = (rank + dr, file + df);
+            if self.piece_at(hr, hf) == Some((color, Piece::Horse)) {
+                let (leg_r, leg_f) = if dr.abs() == 2 {
+                    (hr - dr.signum(), hf)
+                } else {
+                    (hr, hf - df.signum())
+                };
+                if self.piece_at(leg_r, leg_f).is_none() {
+                    attackers.push((hr as usize, hf as usize));
+                }
+            }
+        }
+
+        // Soldiers attack forwards, and sideways once across the river
+        let (forward, crossed) = match color {
+            Color::Red => (-1, rank <= 4),
+            Color::Black => (1, rank >= 5),
+        };
+        if self.piece_at(rank - forward, file) == Some((color, Piece::Soldier)) {
+            attackers.push(((rank - forward) as usize, file as usize));
+        }
+        if crossed {
+            for df in [1, -1] {
+                if self.piece_at(rank, file + df) == Some((color, Piece::Soldier)) {
+                    attackers.push((rank as usize, (file + df) as usize));
+                }
+            }
+        }
+
+        if is_in_palace(target, color) {
+            for (dr, df) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                if self.piece_at(rank + dr, file + df) == Some((color, Piece::General)) {
+                    attackers.push(((rank + dr) as usize, (file + df) as usize));
+                }
+            }
+            for (dr, df) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+                if self.piece_at(rank + dr, file + df) == Some((color, Piece::Advisor)) {
+                    attackers.push(((rank + dr) as usize, (file + df) as usize));
+                }
+            }
+        }
+
+        // Elephants never cross the river and are stopped by a blocked eye
+        if is_own_side(target, color) {
+            for (dr, df) in [(2, 2), (2, -2), (-2, 2), (-2, -2)] {
+                if self.piece_at(rank + dr, file + df) == Some((color, Piece::Elephant))
+                    && self.piece_at(rank + dr / 2, file + df / 2).is_none()
+                {
+                    attackers.push(((rank + dr) as usize, (file + df) as usize));
+                }
+            }
+        }
+
+        attackers
+    }
+
+    fn square_at(&self, rank: i32, file: i32) -> Option<&Square> {
+        if (0..10).contains(&rank) && (0..9).contains(&file) {
+            Some(&self.squares[rank as usize][file as usize])
+        } else {
+            None
+        }
+    }
+
+    fn piece_at(&self, rank: i32, file: i32) -> Option<(Color, Piece)> {
+        self.square_at(rank, file).and_then(|square| square.piece)
+    }
+}
+
+impl Color {
+    pub fn opponent(self) -> Color {
+        match self {
+            Color::Red => Color::Black,
+            Color::Black => Color::Red,
+        }
+    }
+}
+
+// FEN letter of a piece: upper case for Red, lower case for Black
+pub fn piece_letter(color: Color, piece: Piece) -> char {
+    let letter = match piece {
+        Piece::General => 'k',
+        Piece::Advisor => 'a',
+        Piece::Elephant => 'b',
+        Piece::Horse => 'n',
+        Piece::Chariot => 'r',
+        Piece::Cannon => 'c',
+        Piece::Soldier => 'p',
+    };
+    match color {
+        Color::Red => letter.to_ascii_uppercase(),
+        Color::Black => letter,
+    }
+}
+
+fn is_in_palace(pos: (usize, usize), color: Color) -> bool {
+    let ranks = match color {
+        Color::Red => 7..10,
+        Color::Black => 0..3,
+    };
+    ranks.contains(&pos.0) && (3..6).contains(&pos.1)
+}
+
+fn is_own_side(pos: (usize, usize), color: Color) -> bool {
+    match color {
+        Color::Red => pos.0 >= 5,
+        Color::Black => pos.0 <= 4,
     }
 }
 
@@ -368,174 +565,3 @@ impl Board {
 //         Ok(())
 //     }
 // }
-
-// Generate moves for a piece
-fn generate_piece_moves(board: &Board, pos: (usize, usize)) -> Vec<(usize, usize)> {
-    let piece = board.squares[pos.0][pos.1].piece.unwrap();
-    let (color, piece_type) = piece;
-    let mut moves = Vec::new();
-
-    match piece_type {
-        Piece::General => {
-            // General can move one square in any direction (horizontally or vertically)
-            for (dr, dc) in [(0, 1), (0, -1), (1, 0), (-1, 0)] {
-                let new_rank = pos.0 as i32 + dr;
-                let new_file = pos.1 as i32 + dc;
-                if new_rank >= 0 && new_rank < 10 && new_file >= 0 && new_file < 9 {
-                    let new_pos = (new_rank as usize, new_file as usize);
-                    if board.squares[new_pos.0][new_pos.1].piece.is_none()
-                        || board.squares[new_pos.0][new_pos.1].piece.unwrap().0 != color
-                    {
-                        moves.push(new_pos);
-                    }
-                }
-            }
-        }
-        Piece::Advisor => {
-            // Advisor can move one square diagonally
-            for (dr, dc) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
-                let new_rank = pos.0 as i32 + dr;
-                let new_file = pos.1 as i32 + dc;
-                if new_rank >= 0 && new_rank < 10 && new_file >= 0 && new_file < 9 {
-                    let new_pos = (new_rank as usize, new_file as usize);
-                    if board.squares[new_pos.0][new_pos.1].piece.is_none()
-                        || board.squares[new_pos.0][new_pos.1].piece.unwrap().0 != color
-                    {
-                        moves.push(new_pos);
-                    }
-                }
-            }
-        }
-        Piece::Elephant => {
-            // Elephant can move two squares diagonally
-            for (dr, dc) in [(2, 2), (2, -2), (-2, 2), (-2, -2)] {
-                let new_rank = pos.0 as i32 + dr;
-                let new_file = pos.1 as i32 + dc;
-                if new_rank >= 0 && new_rank < 10 && new_file >= 0 && new_file < 9 {
-                    let new_pos = (new_rank as usize, new_file as usize);
-                    if board.squares[new_pos.0][new_pos.1].piece.is_none()
-                        || board.squares[new_pos.0][new_pos.1].piece.unwrap().0 != color
-                    {
-                        moves.push(new_pos);
-                    }
-                }
-            }
-        }
-        Piece::Horse => {
-            // Horse can move one square horizontally or vertically, then one square diagonally
-            for (dr, dc) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
-                let mid_rank = pos.0 as i32 + dr;
-                let mid_file = pos.1 as i32 + dc;
-                if mid_rank >= 0 && mid_rank < 10 && mid_file >= 0 && mid_file < 9 {
-                    for (ddr, ddc) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
-                        let new_rank = mid_rank + ddr;
-                        let new_file = mid_file + ddc;
-                        if new_rank >= 0 && new_rank < 10 && new_file >= 0 && new_file < 9 {
-                            let new_pos = (new_rank as usize, new_file as usize);
-                            if board.squares[new_pos.0][new_pos.1].piece.is_none()
-                                || board.squares[new_pos.0][new_pos.1].piece.unwrap().0 != color
-                            {
-                                moves.push(new_pos);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        Piece::Chariot => {
-            // Chariot can move any number of squares horizontally or vertically
-            for (dr, dc) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
-                let mut new_rank = pos.0 as i32 + dr;
-                let mut new_file = pos.1 as i32 + dc;
-                while new_rank >= 0 && new_rank < 10 && new_file >= 0 && new_file < 9 {
-                    let new_pos = (new_rank as usize, new_file as usize);
-                    if board.squares[new_pos.0][new_pos.1].piece.is_none()
-                        || board.squares[new_pos.0][new_pos.1].piece.unwrap().0 != color
-                    {
-                        moves.push(new_pos);
-                    }
-                    if board.squares[new_pos.0][new_pos.1].piece.is_some() {
-                        break;
-                    }
-                    new_rank += dr;
-                    new_file += dc;
-                }
-            }
-        }
-        Piece::Cannon => {
-            // Cannon can move any number of squares horizontally or vertically, but must jump over exactly one piece
-            for (dr, dc) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
-                let mut new_rank = pos.0 as i32 + dr;
-                let mut new_file = pos.1 as i32 + dc;
-                let mut jumped = false;
-                while new_rank >= 0 && new_rank < 10 && new_file >= 0 && new_file < 9 {
-                    let new_pos = (new_rank as usize, new_file as usize);
-                    if board.squares[new_pos.0][new_pos.1].piece.is_none() {
-                        if jumped {
-                            moves.push(new_pos);
-                        }
-                    } else if board.squares[new_pos.0][new_pos.1].piece.unwrap().0 != color {
-                        if !jumped {
-                            jumped = true;
-                        } else {
-                            moves.push(new_pos);
-                            break;
-                        }
-                    } else {
-                        break;
-                    }
-                    new_rank += dr;
-                    new_file += dc;
-                }
-            }
-        }
-        Piece::Soldier => {
-            // Soldier can move one square forward, but captures diagonally
-            if color == Color::Red {
-                let new_rank = pos.0 as i32 + 1;
-                let new_file = pos.1 as i32;
-                if new_rank >= 0 && new_rank < 10 && new_file >= 0 && new_file < 9 {
-                    let new_pos = (new_rank as usize, new_file as usize);
-                    if board.squares[new_pos.0][new_pos.1].piece.is_none() {
-                        moves.push(new_pos);
-                    }
-                }
-                for (dr, dc) in [(1, 1), (1, -1)] {
-                    let new_rank = pos.0 as i32 + dr;
-                    let new_file = pos.1 as i32 + dc;
-                    if new_rank >= 0 && new_rank < 10 && new_file >= 0 && new_file < 9 {
-                        let new_pos = (new_rank as usize, new_file as usize);
-                        if board.squares[new_pos.0][new_pos.1].piece.is_some()
-                            && board.squares[new_pos.0][new_pos.1].piece.unwrap().0 != color
-                        {
-                            moves.push(new_pos);
-                        }
-                    }
-                }
-            } else {
-                let new_rank = pos.0 as i32 - 1;
-                let new_file = pos.1 as i32;
-                if new_rank >= 0 && new_rank < 10 && new_file >= 0 && new_file < 9 {
-                    let new_pos = (new_rank as usize, new_file as usize);
-                    if board.squares[new_pos.0][new_pos.1].piece.is_none() {
-                        moves.push(new_pos);
-                    }
-                }
-                for (dr, dc) in [(-1, 1), (-1, -1)] {
-                    let new_rank = pos.0 as i32 + dr;
-                    let new_file = pos.1 as i32 + dc;
-                    if new_rank >= 0 && new_rank < 10 && new_file >= 0 && new_file < 9 {
-                        let new_pos = (new_rank as usize, new_file as usize);
-                        if board.squares[new_pos.0][new_pos.1].piece.is_some()
-                            && board.squares[new_pos.0][new_pos.1].piece.unwrap().0 != color
-                        {
-                            moves.push(new_pos);
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    moves
-}
@@ -0,0 +1,299 @@
+// Opening book: a file of fixed-size entries sorted by position key, so a
+// probe is a binary search. Each entry is
+//
+//   key     u64  canonical key of the position (see canonical_key)
+//   move    u16  from square * 90 + to square, squares as rank * 9 + file
+//   weight  u16  relative frequency of the move
+//
+// all big-endian, after an 8-byte magic. Several entries share a key when a
+// position has more than one book move. A position and its left-right mirror
+// image share their entries, stored for whichever of the two has the smaller
+// Zobrist hash.
+use std::collections::HashMap;
+use std::fs;
+use rand::Rng;
+use crate::board::Board;
+use crate::moves::{generate_legal_moves, Move};
+use crate::search::compute_hash;
+
+// Version 2 stores a position and its mirror image once, under canonical_key.
+// Version 1 listed the moves under the Zobrist hash of each image, which
+// version 2 probes would misread.
+const MAGIC: &[u8; 8] = b"XQBOOK2\0";
+const OLD_MAGIC: &[u8; 8] = b"XQBOOK1\0";
+const ENTRY_SIZE: usize = 12;
+pub const MAX_VARIETY: u32 = 100;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub mv: Move,
+    pub weight: u16,
+}
+
+impl BookEntry {
+    // An entry for `mv` played in `board`, stored in canonical orientation
+    pub fn new(board: &Board, mv: &Move, weight: u16) -> Self {
+        let (key, mirrored) = canonical_key(board);
+        let mv = if mirrored { mv.mirror_files() } else { mv.clone() };
+        BookEntry { key, mv, weight }
+    }
+}
+
+// The Zobrist hash of the position or of its mirror image, whichever is
+// smaller, and whether it was the mirror image's
+pub fn canonical_key(board: &Board) -> (u64, bool) {
+    let key = compute_hash(board);
+    let mirror_key = compute_hash(&board.mirror_files());
+    if mirror_key < key {
+        (mirror_key, true)
+    } else {
+        (key, false)
+    }
+}
+
+#[derive(Default)]
+pub struct Book {
+    entries: Vec<BookEntry>, // sorted by key, then by descending weight
+}
+
+impl Book {
+    pub fn new(mut entries: Vec<BookEntry>) -> Self {
+        entries.sort_by(|a, b| a.key.cmp(&b.key).then(b.weight.cmp(&a.weight)));
+        Book { entries }
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|e| format!("cannot read book {}: {}", path, e))?;
+        Self::from_bytes(&data).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_bytes()).map_err(|e| format!("cannot write book {}: {}", path, e))
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        if data.starts_with(OLD_MAGIC) {
+            return Err("book was built by an older version, rebuild it with `book build`".to_string());
+        }
+        if !data.starts_with(MAGIC) {
+            return Err("not an opening book".to_string());
+        }
+        let body = &data[MAGIC.len()..];
+        if !body.len().is_multiple_of(ENTRY_SIZE) {
+            return Err("book is truncated".to_string());
+        }
+        let mut entries = Vec::with_capacity(body.len() / ENTRY_SIZE);
+        for chunk in body.chunks_exact(ENTRY_SIZE) {
+            let key = u64::from_be_bytes(chunk[0..8].try_into().unwrap());
+            let mv = decode_move(u16::from_be_bytes([chunk[8], chunk[9]]))
+                .ok_or_else(|| "book entry has an invalid move".to_string())?;
+            let weight = u16::from_be_bytes([chunk[10], chunk[11]]);
+            entries.push(BookEntry { key, mv, weight });
+        }
+        Ok(Book::new(entries))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(MAGIC.len() + self.entries.len() * ENTRY_SIZE);
+        data.extend_from_slice(MAGIC);
+        for entry in &self.entries {
+            data.extend_from_slice(&entry.key.to_be_bytes());
+            data.extend_from_slice(&encode_move(&entry.mv).to_be_bytes());
+            data.extend_from_slice(&entry.weight.to_be_bytes());
+        }
+        data
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[BookEntry] {
+        &self.entries
+    }
+
+    // Legal book moves for the position with their weights, heaviest first
+    pub fn probe(&self, board: &Board) -> Vec<(Move, u16)> {
+        let (key, mirrored) = canonical_key(board);
+        let first = self.entries.partition_point(|entry| entry.key < key);
+        let legal = generate_legal_moves(board);
+        self.entries[first..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .map(|entry| {
+                let mv = if mirrored { entry.mv.mirror_files() } else { entry.mv.clone() };
+                (mv, entry.weight)
+            })
+            .filter(|(mv, weight)| *weight > 0 && legal.contains(mv))
+            .filter(|(mv, _)| board.clone().make_move(mv.from, mv.to))
+            .collect()
+    }
+
+    // Picks a book move. Variety 0 always plays the heaviest move, 100 picks
+    // in proportion to the weights, and values in between favour heavy moves
+    // more strongly the lower they are.
+    pub fn choose(&self, board: &Board, variety: u32, rng: &mut impl Rng) -> Option<Move> {
+        let moves = self.probe(board);
+        let variety = variety.min(MAX_VARIETY);
+        if variety == 0 {
+            return moves.into_iter().next().map(|(mv, _)| mv);
+        }
+        // Weights are scaled to the heaviest first, so the powers stay within
+        // 0..=1 instead of overflowing for low variety
+        let exponent = MAX_VARIETY as f64 / variety as f64;
+        let heaviest = moves.first()?.1 as f64;
+        let scores: Vec<f64> = moves
+            .iter()
+            .map(|(_, weight)| (*weight as f64 / heaviest).powf(exponent))
+            .collect();
+        let total: f64 = scores.iter().sum();
+        if !total.is_finite() || total <= 0.0 {
+            return moves.into_iter().next().map(|(mv, _)| mv);
+        }
+        let mut pick = rng.gen_range(0.0..total);
+        for ((mv, _), score) in moves.iter().zip(&scores) {
+            if pick < *score {
+                return Some(mv.clone());
+            }
+            pick -= score;
+        }
+        moves.last().map(|(mv, _)| mv.clone())
+    }
+}
+
+fn encode_move(mv: &Move) -> u16 {
+    let from = mv.from.0 * 9 + mv.from.1;
+    let to = mv.to.0 * 9 + mv.to.1;
+    (from * 90 + to) as u16
+}
+
+fn decode_move(code: u16) -> Option<Move> {
+    let (from, to) = (code as usize / 90, code as usize % 90);
+    if from >= 90 {
+        return None;
+    }
+    Some(Move::new((from / 9, from % 9), (to / 9, to % 9)))
+}
+
+pub struct BuildOptions {
+    pub max_ply: usize,   // positions deeper than this are not recorded
+    pub min_count: u32,   // a move must have been played this often
+    pub min_score: u32,   // and score at least this percentage for its side
+}
+
+impl Default for BuildOptions {
+    fn default() -> Self {
+        BuildOptions {
+            max_ply: 20,
+            min_count: 2,
+            min_score: 25,
+        }
+    }
+}
+
+// Results of a move from the point of view of the side that played it
+#[derive(Default)]
+struct MoveStats {
+    wins: u32,
+    draws: u32,
+    losses: u32,
+}
+
+impl MoveStats {
+    fn count(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    fn points(&self) -> u32 {
+        2 * self.wins + self.draws
+    }
+}
+
+// Collects move statistics from games and turns them into a book. Moves are
+// counted by canonical key, so mirror images add up.
+pub struct BookBuilder {
+    options: BuildOptions,
+    positions: HashMap<u64, Vec<(Move, MoveStats)>>,
+    games: usize,
+}
+
+impl BookBuilder {
+    pub fn new(options: BuildOptions) -> Self {
+        BookBuilder {
+            options,
+            positions: HashMap::new(),
+            games: 0,
+        }
+    }
+
+    pub fn games(&self) -> usize {
+        self.games
+    }
+
+    pub fn positions(&self) -> usize {
+        self.positions.len()
+    }
+
+    // Replays one game, recording each move up to the ply limit. `result` is
+    // a PGN result; unfinished games ("*") count as draws. The whole game is
+    // checked first, so one with an illegal move leaves the book untouched.
+    pub fn add_game(&mut self, start: &Board, moves: &[Move], result: &str) -> Result<(), String> {
+        let mut board = start.clone();
+        let mut entries = Vec::new();
+        for (ply, mv) in moves.iter().enumerate() {
+            let legal = generate_legal_moves(&board).contains(mv) && board.clone().make_move(mv.from, mv.to);
+            if !legal {
+                return Err(format!("ply {}: illegal move {}", ply + 1, mv));
+            }
+            if ply < self.options.max_ply {
+                entries.push((BookEntry::new(&board, mv, 0), board.red_to_move));
+            }
+            board.make_move(mv.from, mv.to);
+        }
+
+        for (BookEntry { key, mv: canonical, .. }, red_to_move) in entries {
+            let moves = self.positions.entry(key).or_default();
+            let index = match moves.iter().position(|(known, _)| *known == canonical) {
+                Some(index) => index,
+                None => {
+                    moves.push((canonical, MoveStats::default()));
+                    moves.len() - 1
+                }
+            };
+            let stats = &mut moves[index].1;
+            match (result, red_to_move) {
+                ("1-0", true) | ("0-1", false) => stats.wins += 1,
+                ("1-0", false) | ("0-1", true) => stats.losses += 1,
+                _ => stats.draws += 1,
+            }
+        }
+        self.games += 1;
+        Ok(())
+    }
+
+    // Moves that pass the count and score filters, weighted by their points
+    // (two per win, one per draw)
+    pub fn build(&self) -> Book {
+        let mut entries = Vec::new();
+        for (&key, moves) in &self.positions {
+            for (mv, stats) in moves {
+                let count = stats.count();
+                if count < self.options.min_count || stats.points() * 50 < self.options.min_score * count {
+                    continue;
+                }
+                let weight = stats.points().clamp(1, u16::MAX as u32) as u16;
+                entries.push(BookEntry {
+                    key,
+                    mv: mv.clone(),
+                    weight,
+                });
+            }
+        }
+        Book::new(entries)
+    }
+}
@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::Path;
+use chess_engine::book::{BookBuilder, BuildOptions};
+use chess_engine::pgn::{parse_pgn, parse_pgn_games};
+use chess_engine::Game;
+use crate::log::diag;
+
+pub const DEFAULT_BOOK_FILE: &str = "book.bin";
+
+pub struct BuildArgs {
+    pub output: String,
+    pub inputs: Vec<String>,
+    pub options: BuildOptions,
+}
+
+// book build [-o file] [--depth plies] [--min-count n] [--min-score percent] <games...>
+pub fn parse_build_args(args: &[String]) -> Result<BuildArgs, String> {
+    let mut parsed = BuildArgs {
+        output: DEFAULT_BOOK_FILE.to_string(),
+        inputs: Vec::new(),
+        options: BuildOptions::default(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", name))
+        };
+        match arg.as_str() {
+            "-o" | "--output" => parsed.output = value(arg)?,
+            "--depth" => parsed.options.max_ply = parse_number(arg, &value(arg)?)?,
+            "--min-count" => parsed.options.min_count = parse_number(arg, &value(arg)?)?,
+            "--min-score" => parsed.options.min_score = parse_number(arg, &value(arg)?)?,
+            option if option.starts_with('-') => return Err(format!("unknown option {}", option)),
+            path => parsed.inputs.push(path.to_string()),
+        }
+    }
+    if parsed.inputs.is_empty() {
+        return Err("no game files given".to_string());
+    }
+    Ok(parsed)
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid {} value {}", name, value))
+}
+
+// Builds a book from PGN files and plain move lists (one game per line, e.g.
+// "h2e2 h9g7 h0g2 1-0"). Games with illegal moves are reported and skipped.
+pub fn run_build(args: BuildArgs) -> Result<(), String> {
+    let mut builder = BookBuilder::new(args.options);
+    let mut skipped = 0;
+    for path in &args.inputs {
+        let (games, unreadable) = read_games(path)?;
+        skipped += unreadable;
+        for (index, game) in games.iter().enumerate() {
+            let result = game.header("Result").unwrap_or("*");
+            if let Err(e) = builder.add_game(&game.start, &game.mainline_moves(), result) {
+                diag!("{}: game {}: {}", path, index + 1, e);
+                skipped += 1;
+            }
+        }
+    }
+
+    let book = builder.build();
+    book.save(&args.output)?;
+    println!(
+        "{} games ({} skipped), {} positions, {} book entries written to {}",
+        builder.games(),
+        skipped,
+        builder.positions(),
+        book.len(),
+        args.output
+    );
+    Ok(())
+}
+
+// The games in a file, and how many PGN games or move list lines could not
+// be read
+fn read_games(path: &str) -> Result<(Vec<Game>, usize), String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let is_pgn = Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pgn"))
+        || text.trim_start().starts_with('[');
+    if is_pgn {
+        let mut games = Vec::new();
+        let mut unreadable = 0;
+        for game in parse_pgn_games(&text) {
+            match game {
+                Ok(game) => games.push(game),
+                Err(e) => {
+                    diag!("{}: {}", path, e);
+                    unreadable += 1;
+                }
+            }
+        }
+        return Ok((games, unreadable));
+    }
+
+    let mut games = Vec::new();
+    let mut unreadable = 0;
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_pgn(line) {
+            Ok(parsed) => games.extend(parsed),
+            Err(e) => {
+                diag!("{}: line {}: {}", path, number + 1, e);
+                unreadable += 1;
+            }
+        }
+    }
+    Ok((games, unreadable))
+}
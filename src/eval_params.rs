@@ -0,0 +1,182 @@
+// Evaluation weights as data, so they can be changed without recompiling.
+// The defaults are the embedded default_eval.toml; a TOML or JSON file only
+// has to list the entries it changes, and single entries can be set by a
+// dotted path such as "chariot.value" or "horse.psq_mg.2.4".
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::evaluation::Score;
+
+const DEFAULT_PARAMS: &str = include_str!("default_eval.toml");
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PieceParams {
+    pub value: Score,
+    pub psq_mg: [[i32; 9]; 10],
+    pub psq_eg: [[i32; 9]; 10],
+    // by number of safe squares; pieces without a table score no mobility
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mobility: Vec<Score>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EvalParams {
+    pub cannon_platform: Score,
+    pub same_file_generals: Score,
+    pub protector: Score,
+    pub exposed_general: Score,
+    pub palace_attacker: Score,
+    pub palace_horse: Score,
+    pub central_cannon: Score,
+    pub empty_headed_cannon: Score,
+    pub iron_bolt: Score,
+    pub open_palace_file: Score,
+    pub missing_advisor: Score,
+    pub soldier: PieceParams,
+    pub cannon: PieceParams,
+    pub horse: PieceParams,
+    pub elephant: PieceParams,
+    pub advisor: PieceParams,
+    pub chariot: PieceParams,
+    pub general: PieceParams,
+}
+
+static DEFAULTS: LazyLock<EvalParams> =
+    LazyLock::new(|| toml::from_str(DEFAULT_PARAMS).expect("embedded default_eval.toml is invalid"));
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        DEFAULTS.clone()
+    }
+}
+
+impl EvalParams {
+    // The embedded defaults, without a copy
+    pub fn defaults() -> &'static EvalParams {
+        &DEFAULTS
+    }
+
+    // Reads a .json file as JSON and anything else as TOML. Entries the file
+    // leaves out keep their default values.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        let overrides: Value = if is_json(path) {
+            serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?
+        } else {
+            toml::from_str(&text).map_err(|e| format!("{}: {}", path, e))?
+        };
+        let mut merged = to_value(EvalParams::defaults());
+        merge(&mut merged, overrides);
+        serde_json::from_value(merged).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    // Writes every entry, as JSON for a .json file and TOML otherwise
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let text = if is_json(path) {
+            serde_json::to_string_pretty(self).map_err(|e| e.to_string())?
+        } else {
+            toml::to_string(self).map_err(|e| e.to_string())?
+        };
+        fs::write(path, text).map_err(|e| format!("cannot write {}: {}", path, e))
+    }
+
+    // Sets one entry: "cannon_platform 6 4", "chariot.value 610 630" or
+    // "horse.psq_eg.2.4 20". A weight given one number uses it for both
+    // middlegame and endgame.
+    pub fn set(&mut self, path: &str, numbers: &[i32]) -> Result<(), String> {
+        let mut root = to_value(self);
+        let mut target = &mut root;
+        for key in path.split('.') {
+            target = match target {
+                Value::Object(map) => map.get_mut(key),
+                Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get_mut(i)),
+                _ => None,
+            }
+            .ok_or_else(|| format!("unknown evaluation parameter {}", path))?;
+        }
+        *target = match (&*target, numbers) {
+            (Value::Number(_), [value]) => Value::from(*value),
+            (Value::Array(pair), [value]) if pair.len() == 2 => Value::from(vec![*value, *value]),
+            (Value::Array(pair), [mg, eg]) if pair.len() == 2 => Value::from(vec![*mg, *eg]),
+            _ => return Err(format!("{} cannot be set to {:?}", path, numbers)),
+        };
+        *self = serde_json::from_value(root).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    // Every weight as one flat list, each Score as its mg and eg halves. The
+    // order is fixed but otherwise arbitrary; from_vector reads it back.
+    pub fn to_vector(&self) -> Vec<i32> {
+        let mut values = Vec::new();
+        flatten(&to_value(self), &mut values);
+        values
+    }
+
+    pub fn from_vector(values: &[i32]) -> Result<Self, String> {
+        let mut root = to_value(EvalParams::defaults());
+        let mut values = values.iter();
+        unflatten(&mut root, &mut values)?;
+        if values.next().is_some() {
+            return Err("too many evaluation parameters".to_string());
+        }
+        serde_json::from_value(root).map_err(|e| e.to_string())
+    }
+
+    // Every weight set to zero
+    pub fn zero() -> Self {
+        let count = EvalParams::defaults().to_vector().len();
+        EvalParams::from_vector(&vec![0; count]).expect("a zero vector has the right length")
+    }
+}
+
+fn is_json(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+fn to_value(params: &EvalParams) -> Value {
+    serde_json::to_value(params).expect("evaluation parameters always serialize")
+}
+
+fn flatten(value: &Value, values: &mut Vec<i32>) {
+    match value {
+        Value::Object(map) => map.values().for_each(|item| flatten(item, values)),
+        Value::Array(items) => items.iter().for_each(|item| flatten(item, values)),
+        Value::Number(number) => values.push(number.as_i64().unwrap_or(0) as i32),
+        _ => {}
+    }
+}
+
+fn unflatten<'a>(value: &mut Value, values: &mut impl Iterator<Item = &'a i32>) -> Result<(), String> {
+    match value {
+        Value::Object(map) => map.values_mut().try_for_each(|item| unflatten(item, values)),
+        Value::Array(items) => items.iter_mut().try_for_each(|item| unflatten(item, values)),
+        Value::Number(_) => {
+            *value = Value::from(*values.next().ok_or("too few evaluation parameters")?);
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+// Recursively replaces the entries of `base` that `overrides` mentions
+fn merge(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
@@ -1,281 +1,678 @@
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub};
+use serde::{Deserialize, Serialize};
+use crate::attacks::{first_square, square_bit, AttackMaps};
 use crate::board::{Board, Color, Piece};
+use crate::eval_params::{EvalParams, PieceParams};
+use crate::moves::{for_each_piece_square, generate_piece_moves, piece_attacks};
 
-const SOLDIER_VALUE: i32 = 30;
-const CANNON_VALUE: i32 = 285;
-const HORSE_VALUE: i32 = 270;
-const ELEPHANT_VALUE: i32 = 120;
-const ADVISOR_VALUE: i32 = 120;
-const CHARIOT_VALUE: i32 = 600;
-const GENERAL_VALUE: i32 = 6000;
-
-// Piece-Square tables
-const SOLDIER_BONUS_RED: [[i32; 9]; 10] = [
-    [0,  0,  0,  0,  0,  0,  0,  0,  0],
-    [0,  0,  0,  0,  0,  0,  0,  0,  0],
-    [0,  0,  0,  0,  0,  0,  0,  0,  0],
-    [2,  4,  6,  6,  6,  6,  6,  4,  2],
-    [6,  8,  10, 12, 12, 12, 10, 8,  6],
-    [10, 12, 14, 16, 18, 16, 14, 12, 10],
-    [14, 16, 18, 20, 20, 20, 18, 16, 14],
-    [18, 20, 22, 24, 24, 24, 22, 20, 18],
-    [22, 24, 26, 28, 28, 28, 26, 24, 22],
-    [26, 28, 30, 32, 32, 32, 30, 28, 26],
-];
+// Every weight has a middlegame and an endgame value; the evaluation blends
+// the two by how much of the heavy material (chariots, horses, cannons) is
+// still on the board. Parameter files write a weight as [mg, eg].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "[i32; 2]", into = "[i32; 2]")]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
 
-const CHARIOT_BONUS: [[i32; 9]; 10] = [
-    [14, 14, 12, 18, 16, 18, 12, 14, 14],
-    [16, 20, 18, 24, 26, 24, 18, 20, 16],
-    [12, 12, 12, 18, 18, 18, 12, 12, 12],
-    [12, 18, 16, 22, 22, 22, 16, 18, 12],
-    [12, 14, 12, 18, 18, 18, 12, 14, 12],
-    [12, 16, 14, 20, 20, 20, 14, 16, 12],
-    [6,  10, 8,  14, 14, 14, 8,  10, 6],
-    [4,  8,  6,  14, 12, 14, 6,  8,  4],
-    [8,  4,  8,  16, 8,  16, 8,  4,  8],
-    [-2, 10, 6,  14, 12, 14, 6,  10, -2],
-];
+pub const fn s(mg: i32, eg: i32) -> Score {
+    Score { mg, eg }
+}
 
-const HORSE_BONUS: [[i32; 9]; 10] = [
-    [4,  8,  16, 12, 4,  12, 16, 8,  4],
-    [4,  10, 28, 16, 8,  16, 28, 10, 4],
-    [12, 14, 16, 20, 18, 20, 16, 14, 12],
-    [8,  24, 18, 24, 20, 24, 18, 24, 8],
-    [6,  16, 14, 18, 16, 18, 14, 16, 6],
-    [4,  12, 16, 14, 12, 14, 16, 12, 4],
-    [2,  6,  8,  6,  10, 6,  8,  6,  2],
-    [-2, 4,  4,  4,  4,  4,  4,  4,  -2],
-    [0,  2,  4,  4,  -2, 4,  4,  2,  0],
-    [0,  -4, 0,  0,  0,  0,  0,  -4, 0],
-];
+impl From<[i32; 2]> for Score {
+    fn from([mg, eg]: [i32; 2]) -> Score {
+        s(mg, eg)
+    }
+}
+
+impl From<Score> for [i32; 2] {
+    fn from(score: Score) -> [i32; 2] {
+        [score.mg, score.eg]
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+    fn add(self, other: Score) -> Score {
+        s(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, other: Score) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+    fn sub(self, other: Score) -> Score {
+        s(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl Mul<i32> for Score {
+    type Output = Score;
+    fn mul(self, factor: i32) -> Score {
+        s(self.mg * factor, self.eg * factor)
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+    fn neg(self) -> Score {
+        s(-self.mg, -self.eg)
+    }
+}
+
+impl Score {
+    pub fn taper(self, phase: i32) -> i32 {
+        (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+// Game phase: MAX_PHASE with all heavy pieces on the board, 0 without any
+const CHARIOT_PHASE: i32 = 4;
+const HORSE_PHASE: i32 = 2;
+const CANNON_PHASE: i32 = 2;
+pub const MAX_PHASE: i32 = 2 * (2 * CHARIOT_PHASE + 2 * HORSE_PHASE + 2 * CANNON_PHASE);
+
+const FLYING_GENERAL_SCORE: i32 = 50000;
+
+// One term of the evaluation, as earned by each side
+#[derive(Clone, Debug, PartialEq)]
+pub struct TermScore {
+    pub name: &'static str,
+    pub red: i32,
+    pub black: i32,
+}
 
-const CANNON_BONUS: [[i32; 9]; 10] = [
-    [6,  4,  0,  -10,-12,-10,0,  4,  6],
-    [2,  2,  0,  -4, -14,-4, 0,  2,  2],
-    [2,  2,  0,  -10,-8, -10,0,  2,  2],
-    [0,  0,  -2, 4,  10, 4,  -2, 0,  0],
-    [0,  0,  0,  2,  8,  2,  0,  0,  0],
-    [-2, 0,  4,  2,  6,  2,  4,  0,  -2],
-    [0,  0,  0,  2,  4,  2,  0,  0,  0],
-    [4,  0,  8,  6,  10, 6,  8,  0,  4],
-    [0,  2,  4,  6,  6,  6,  4,  2,  0],
-    [0,  0,  2,  6,  6,  6,  2,  0,  0],
+// What the evaluation counts for one side. Every term is one of these counts
+// times a weight, so the evaluation is linear in its weights (see
+// coefficients).
+#[derive(Default)]
+struct SideFeatures {
+    pieces: Vec<(Piece, usize, usize)>, // rank as seen from this side
+    mobility: Vec<(Piece, usize)>,      // safe squares of each chariot, horse and cannon
+    cannon_platforms: i32,              // enemy pieces attacked over a screen
+    pinned_screens: i32, // the only piece between two facing generals
+    king_safety: [i32; KING_SAFETY_TERMS], // threats counted negatively
+}
+
+// King safety is the sum of these counts times their weights, in the order
+// of king_safety_weights
+const KING_SAFETY_TERMS: usize = 9;
+const KING_SAFETY_NAMES: [&str; KING_SAFETY_TERMS] = [
+    "palace protectors",    // advisors and elephants in the palace
+    "advanced general",     // ranks the general has left its back rank by
+    "palace attackers",     // enemy chariots, cannons and soldiers hitting the palace
+    "palace horses",        // enemy horses hitting the palace, now or after one move
+    "central cannons",      // enemy cannons on the general's file behind one screen
+    "empty-headed cannons", // the same with nothing in between
+    "iron bolts",           // an empty-headed cannon while a chariot holds the general's side
+    "open palace files",    // palace files without a defender, per enemy chariot
+    "missing advisors",     // per enemy chariot
 ];
 
-const ADVISOR_BONUS: [[i32; 9]; 10] = [
-    [0, 0, 0, 0, 0, 0, 0, 0, 0],
-    [0, 0, 0, 0, 0, 0, 0, 0, 0],
-    [0, 0, 0, 0, 0, 0, 0, 0, 0],
-    [0, 0, 0, 0, 0, 0, 0, 0, 0],
-    [0, 0, 0, 0, 0, 0, 0, 0, 0],
-    [0, 0, 0, 0, 0, 0, 0, 0, 0],
-    [0, 0, 0, 0, 0, 0, 0, 0, 0],
-    [0, 0, 0, 20,0, 20,0, 0, 0],
-    [0, 0, 0, 0, 23,0, 0, 0, 0],
-    [0, 0, 0, 20,0, 20,0, 0, 0],
+fn king_safety_weights(params: &EvalParams) -> [Score; KING_SAFETY_TERMS] {
+    [
+        params.protector,
+        params.exposed_general,
+        params.palace_attacker,
+        params.palace_horse,
+        params.central_cannon,
+        params.empty_headed_cannon,
+        params.iron_bolt,
+        params.open_palace_file,
+        params.missing_advisor,
+    ]
+}
+
+fn king_safety_weights_mut(params: &mut EvalParams) -> [&mut Score; KING_SAFETY_TERMS] {
+    [
+        &mut params.protector,
+        &mut params.exposed_general,
+        &mut params.palace_attacker,
+        &mut params.palace_horse,
+        &mut params.central_cannon,
+        &mut params.empty_headed_cannon,
+        &mut params.iron_bolt,
+        &mut params.open_palace_file,
+        &mut params.missing_advisor,
+    ]
+}
+
+// One side's share of an evaluation term
+type Term = fn(&SideFeatures, &EvalParams) -> Score;
+
+const TERMS: [(&str, Term); 5] = [
+    ("material", |side, params| {
+        side.pieces.iter().fold(Score::default(), |sum, &(piece, _, _)| sum + piece_params(params, piece).value)
+    }),
+    ("piece squares", |side, params| piece_square_score(side, params, None)),
+    ("general file", |side, params| -params.same_file_generals * side.pinned_screens),
+    ("mobility", |side, params| mobility_score(side, params, None)),
+    ("king safety", |side, params| {
+        let weights = king_safety_weights(params);
+        (0..KING_SAFETY_TERMS).fold(Score::default(), |sum, i| sum + weights[i] * side.king_safety[i])
+    }),
 ];
 
-const ELEPHANT_BONUS: [[i32; 9]; 10] = [
-    [0, 0, 0, 0, 0, 0, 0, 0, 0],
-    [0, 0, 0, 0, 0, 0, 0, 0, 0],
-    [0, 0, 0, 0, 0, 0, 0, 0, 0],
-    [0, 0, 0, 0, 0, 0, 0, 0, 0],
-    [0, 0, 0, 0, 0, 0, 0, 0, 0],
-    [0, 0, 20,0, 0, 0, 20,0, 0],
-    [0, 0, 0, 0, 0, 0, 0, 0, 0],
-    [18,0, 0, 0, 23,0, 0, 0, 18],
-    [0, 0, 0, 0, 0, 0, 0, 0, 0],
-    [0, 0, 20,0, 0, 0, 20,0, 0],
+const PIECE_NAMES: [(Piece, &str); 7] = [
+    (Piece::General, "general"),
+    (Piece::Advisor, "advisor"),
+    (Piece::Elephant, "elephant"),
+    (Piece::Horse, "horse"),
+    (Piece::Chariot, "chariot"),
+    (Piece::Cannon, "cannon"),
+    (Piece::Soldier, "soldier"),
 ];
 
-pub fn evaluate_position(board: &Board) -> i32 {
-    let mut score = 0;
-    let mut red_pieces = 0;
-    let mut black_pieces = 0;
+// A side's piece-square score, for one kind of piece or all of them
+fn piece_square_score(side: &SideFeatures, params: &EvalParams, only: Option<Piece>) -> Score {
+    side.pieces
+        .iter()
+        .filter(|&&(piece, _, _)| only.is_none_or(|only| only == piece))
+        .fold(Score::default(), |sum, &(piece, rank, file)| {
+            let table = piece_params(params, piece);
+            sum + s(table.psq_mg[rank][file], table.psq_eg[rank][file])
+        })
+}
 
-    // Heavy penalty for flying general (should never happen due to move validation, but just in case)
-    if board.is_flying_general() {
-        return if board.red_to_move { -50000 } else { 50000 };
+// A side's mobility score, for one kind of piece or all of them; cannons
+// add their platforms
+fn mobility_score(side: &SideFeatures, params: &EvalParams, only: Option<Piece>) -> Score {
+    let wanted = |piece: Piece| only.is_none_or(|only| only == piece);
+    let mut score = side
+        .mobility
+        .iter()
+        .filter(|&&(piece, _)| wanted(piece))
+        .fold(Score::default(), |sum, &(piece, count)| {
+            let table = &piece_params(params, piece).mobility;
+            sum + mobility_index(table, count).map_or(Score::default(), |index| table[index])
+        });
+    if wanted(Piece::Cannon) {
+        score += params.cannon_platform * side.cannon_platforms;
     }
+    score
+}
 
-    // Check for potential flying general threat
-    let (red_general_file, black_general_file) = find_general_files(board);
-    if let (Some(red_file), Some(black_file)) = (red_general_file, black_general_file) {
-        if red_file == black_file {
-            // Penalize having generals on the same file
-            score -= 50;
-        }
-    }
+// The table entry for `count` safe squares; counts past the end of the table
+// use its last entry
+fn mobility_index(table: &[Score], count: usize) -> Option<usize> {
+    table.len().checked_sub(1).map(|last| count.min(last))
+}
 
-    // Evaluate material and position
+fn side_features(board: &Board) -> [SideFeatures; 2] {
+    let mut features: [SideFeatures; 2] = Default::default();
+    let attacks = AttackMaps::new(board);
     for rank in 0..10 {
         for file in 0..9 {
             if let Some((color, piece)) = board.squares[rank][file].piece {
-                let mut piece_value = match piece {
-                    Piece::Soldier => {
-                        SOLDIER_VALUE + if color == Color::Red {
-                            SOLDIER_BONUS_RED[rank][file]
-                        } else {
-                            SOLDIER_BONUS_RED[9 - rank][file]
-                        }
-                    },
-                    Piece::Cannon => {
-                        CANNON_VALUE + if color == Color::Red {
-                            CANNON_BONUS[rank][file]
-                        } else {
-                            CANNON_BONUS[9 - rank][file]
-                        }
-                    },
-                    Piece::Horse => {
-                        HORSE_VALUE + if color == Color::Red {
-                            HORSE_BONUS[rank][file]
-                        } else {
-                            HORSE_BONUS[9 - rank][file]
-                        }
-                    },
-                    Piece::Elephant => {
-                        ELEPHANT_VALUE + if color == Color::Red {
-                            ELEPHANT_BONUS[rank][file]
-                        } else {
-                            ELEPHANT_BONUS[9 - rank][file]
-                        }
-                    },
-                    Piece::Advisor => {
-                        ADVISOR_VALUE + if color == Color::Red {
-                            ADVISOR_BONUS[rank][file]
-                        } else {
-                            ADVISOR_BONUS[9 - rank][file]
-                        }
-                    },
-                    Piece::Chariot => {
-                        CHARIOT_VALUE + if color == Color::Red {
-                            CHARIOT_BONUS[rank][file]
-                        } else {
-                            CHARIOT_BONUS[9 - rank][file]
-                        }
-                    },
-                    Piece::General => GENERAL_VALUE,
-                };
-
-                // Count pieces for endgame detection
-                if color == Color::Red {
-                    red_pieces += 1;
-                } else {
-                    black_pieces += 1;
+                let side = &mut features[side_index(color)];
+                // tables are written from Red's side of the board
+                let table_rank = if color == Color::Red { rank } else { 9 - rank };
+                side.pieces.push((piece, table_rank, file));
+                if matches!(piece, Piece::Chariot | Piece::Horse | Piece::Cannon) {
+                    side.mobility.push((piece, safe_moves(board, &attacks, (rank, file))));
                 }
-
-                // Adjust value based on piece color
-                if color == Color::Black {
-                    piece_value = -piece_value;
+                if piece == Piece::Cannon {
+                    piece_attacks(board, (rank, file), |(r, f)| {
+                        if matches!(board.squares[r][f].piece, Some((owner, _)) if owner != color) {
+                            side.cannon_platforms += 1;
+                        }
+                    });
                 }
-
-                score += piece_value;
             }
         }
     }
 
-    // Endgame adjustments
-    let total_pieces = red_pieces + black_pieces;
-    if total_pieces <= 12 {  // Endgame threshold
-        // Increase value of soldiers in endgame
-        for rank in 0..10 {
-            for file in 0..9 {
-                if let Some((color, Piece::Soldier)) = board.squares[rank][file].piece {
-                    score += if color == Color::Red { 10 } else { -10 };
-                }
-            }
+    for color in [Color::Red, Color::Black] {
+        if let Some(king_pos) = board.find_general(color) {
+            features[side_index(color)].king_safety = king_safety_counts(board, &attacks, king_pos, color);
         }
     }
+    if let Some(owner) = pinned_screen_owner(board) {
+        features[side_index(owner)].pinned_screens = 1;
+    }
+    features
+}
 
-    // Mobility evaluation
-    let moves = crate::moves::generate_legal_moves(board);
-    let mobility_bonus = (moves.len() as i32).saturating_mul(5);
-    score = score.saturating_add(if board.red_to_move { mobility_bonus } else { -mobility_bonus });
+// The evaluation split into its terms, each already blended for the game
+// phase. evaluate_position is the sum of red - black over all terms,
+// negated when Black is to move.
+pub fn evaluate_terms(board: &Board) -> Vec<TermScore> {
+    evaluate_terms_with(board, EvalParams::defaults())
+}
+
+pub fn evaluate_terms_with(board: &Board, params: &EvalParams) -> Vec<TermScore> {
+    let phase = game_phase(board);
+    let features = side_features(board);
+    TERMS
+        .into_iter()
+        .map(|(name, term)| TermScore {
+            name,
+            red: term(&features[0], params).taper(phase),
+            black: term(&features[1], params).taper(phase),
+        })
+        .collect()
+}
 
-    // King safety evaluation
-    if let Some(red_king_pos) = find_king(board, Color::Red) {
-        score = score.saturating_add(evaluate_king_safety(board, red_king_pos, Color::Red));
+// How much each weight contributes to the evaluation from Red's side, in the
+// shape of EvalParams: Red's count minus Black's, times the phase for the
+// middlegame half and MAX_PHASE - phase for the endgame half. Multiplying
+// these with the weights and dividing the sum by MAX_PHASE gives the
+// evaluation up to rounding, which is what the tuner fits.
+pub fn coefficients(board: &Board) -> EvalParams {
+    let phase = game_phase(board);
+    let mut coefficients = EvalParams::zero();
+    for (side, sign) in side_features(board).iter().zip([1, -1]) {
+        let weight = s(phase, MAX_PHASE - phase) * sign;
+        for &(piece, rank, file) in &side.pieces {
+            let piece = piece_params_mut(&mut coefficients, piece);
+            piece.value += weight;
+            piece.psq_mg[rank][file] += weight.mg;
+            piece.psq_eg[rank][file] += weight.eg;
+        }
+        coefficients.same_file_generals += -weight * side.pinned_screens;
+        for &(piece, count) in &side.mobility {
+            let table = &mut piece_params_mut(&mut coefficients, piece).mobility;
+            if let Some(index) = mobility_index(table, count) {
+                table[index] += weight;
+            }
+        }
+        coefficients.cannon_platform += weight * side.cannon_platforms;
+        for (coefficient, count) in king_safety_weights_mut(&mut coefficients).into_iter().zip(side.king_safety) {
+            *coefficient += weight * count;
+        }
     }
-    if let Some(black_king_pos) = find_king(board, Color::Black) {
-        score = score.saturating_sub(evaluate_king_safety(board, black_king_pos, Color::Black));
+    coefficients
+}
+
+pub fn evaluate_position(board: &Board) -> i32 {
+    evaluate_with(board, EvalParams::defaults())
+}
+
+// The evaluation from the side to move's point of view, with the given weights
+pub fn evaluate_with(board: &Board, params: &EvalParams) -> i32 {
+    // should never happen due to move validation, but the side to move
+    // could take the other general
+    if board.is_flying_general() {
+        return FLYING_GENERAL_SCORE;
     }
 
-    // Negate score for black's turn
-    if !board.red_to_move {
-        -score
-    } else {
+    let score: i32 = evaluate_terms_with(board, params).iter().map(|term| term.red - term.black).sum();
+    if board.red_to_move {
         score
+    } else {
+        -score
     }
 }
 
-fn find_king(board: &Board, color: Color) -> Option<(usize, usize)> {
-    for rank in 0..10 {
-        for file in 0..9 {
-            if let Some((piece_color, Piece::General)) = board.squares[rank][file].piece {
-                if piece_color == color {
-                    return Some((rank, file));
-                }
-            }
+// One row of an evaluation trace, before blending for the game phase
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceRow {
+    pub name: String,
+    pub part: bool, // a breakdown of the row above, already counted there
+    pub red: Score,
+    pub black: Score,
+}
+
+// Everything evaluate_with adds up, term by term and side by side
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvalTrace {
+    pub phase: i32,
+    pub red_to_move: bool,
+    pub rows: Vec<TraceRow>,
+    pub flying_general: bool,
+    pub score: i32, // what evaluate_with returns, for the side to move
+}
+
+impl EvalTrace {
+    pub fn red_score(&self) -> i32 {
+        if self.red_to_move {
+            self.score
+        } else {
+            -self.score
         }
     }
-    None
-}
-
-fn evaluate_king_safety(board: &Board, king_pos: (usize, usize), color: Color) -> i32 {
-    let mut safety_score = 0;
-    
-    // Bonus for having advisors and elephants near the king
-    let rank_range = if color == Color::Red { 7..10 } else { 0..3 };
-    let mut protector_count = 0;
-    
-    for rank in rank_range {
-        for file in 3..6 {
-            if let Some((piece_color, piece)) = board.squares[rank][file].piece {
-                if piece_color == color {
-                    match piece {
-                        Piece::Advisor | Piece::Elephant => protector_count += 1,
-                        _ => {}
-                    }
-                }
+}
+
+// The terms of evaluate_terms_with before blending, with the piece squares
+// and mobility split by piece, king safety split into its parts, and the
+// flying-general score, which replaces all other
+// terms when the side to move could take the enemy general
+pub fn trace_evaluation(board: &Board, params: &EvalParams) -> EvalTrace {
+    let features = side_features(board);
+    let mut rows = Vec::new();
+    for (name, term) in TERMS {
+        rows.push(TraceRow {
+            name: name.to_string(),
+            part: false,
+            red: term(&features[0], params),
+            black: term(&features[1], params),
+        });
+        let parts: Vec<(&str, [Score; 2])> = match name {
+            "piece squares" => PIECE_NAMES
+                .iter()
+                .map(|&(piece, piece_name)| {
+                    (piece_name, features.each_ref().map(|side| piece_square_score(side, params, Some(piece))))
+                })
+                .collect(),
+            "mobility" => PIECE_NAMES
+                .iter()
+                .filter(|&&(piece, _)| !piece_params(params, piece).mobility.is_empty() || piece == Piece::Cannon)
+                .map(|&(piece, piece_name)| {
+                    (piece_name, features.each_ref().map(|side| mobility_score(side, params, Some(piece))))
+                })
+                .collect(),
+            "king safety" => {
+                let weights = king_safety_weights(params);
+                KING_SAFETY_NAMES
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &part_name)| (part_name, features.each_ref().map(|side| weights[i] * side.king_safety[i])))
+                    .collect()
             }
+            _ => Vec::new(),
+        };
+        for (part_name, [red, black]) in parts {
+            rows.push(TraceRow {
+                name: part_name.to_string(),
+                part: true,
+                red,
+                black,
+            });
         }
     }
-    
-    safety_score += protector_count * 15;
-    
-    // Penalty for exposed king
-    let (rank, _) = king_pos;
-    let exposed_penalty = if color == Color::Red {
-        if rank <= 7 {
-            0
-        } else {
-            ((rank - 7) as i32) * 10
-        }
+
+    let flying_general = board.is_flying_general();
+    let flying = if flying_general {
+        s(FLYING_GENERAL_SCORE, FLYING_GENERAL_SCORE)
     } else {
-        if rank >= 2 {
-            ((rank - 2) as i32) * 10
-        } else {
-            0
+        Score::default()
+    };
+    let (red, black) = if board.red_to_move {
+        (flying, Score::default())
+    } else {
+        (Score::default(), flying)
+    };
+    rows.push(TraceRow {
+        name: "flying general".to_string(),
+        part: false,
+        red,
+        black,
+    });
+
+    EvalTrace {
+        phase: game_phase(board),
+        red_to_move: board.red_to_move,
+        rows,
+        flying_general,
+        score: evaluate_with(board, params),
+    }
+}
+
+// A table with the middlegame, endgame and blended value of every row for
+// each side and for Red - Black, then the totals
+impl fmt::Display for EvalTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let phase = self.phase;
+        let cells = |score: Score| format!("{:>6} {:>6} {:>6}", score.mg, score.eg, score.taper(phase));
+        let line = format!("{:-<24}+{:-<22}+{:-<22}+{:-<21}", "", "", "", "");
+        writeln!(f, "Phase {} ({} in the opening, 0 without chariots, horses and cannons)", phase, MAX_PHASE)?;
+        let difference = format!("{:^20}", "Red - Black");
+        writeln!(f, "{:<24}| {:^20} | {:^20} | {}", "Term", "Red", "Black", difference.trim_end())?;
+        let halves = format!("{:>6} {:>6} {:>6}", "MG", "EG", "Blend");
+        writeln!(f, "{:<24}| {} | {} | {}", "", halves, halves, halves)?;
+        writeln!(f, "{}", line)?;
+
+        let mut totals = [Score::default(); 2];
+        let mut blended = [0; 2];
+        for row in &self.rows {
+            let name = if row.part { format!("  {}", row.name) } else { row.name.clone() };
+            let difference = cells(row.red - row.black);
+            writeln!(f, "{:<24}| {} | {} | {}", name, cells(row.red), cells(row.black), difference)?;
+            if !row.part {
+                totals[0] += row.red;
+                totals[1] += row.black;
+                blended[0] += row.red.taper(phase);
+                blended[1] += row.black.taper(phase);
+            }
         }
+
+        writeln!(f, "{}", line)?;
+        let total = |score: Score, blend: i32| format!("{:>6} {:>6} {:>6}", score.mg, score.eg, blend);
+        writeln!(
+            f,
+            "{:<24}| {} | {} | {}",
+            "total",
+            total(totals[0], blended[0]),
+            total(totals[1], blended[1]),
+            total(totals[0] - totals[1], blended[0] - blended[1])
+        )?;
+        if self.flying_general {
+            writeln!(f, "The generals face each other: the side to move wins, other terms do not count")?;
+        }
+        let side = if self.red_to_move { "Red" } else { "Black" };
+        write!(
+            f,
+            "Evaluation: {:+} for Red, {:+} for the side to move ({})",
+            self.red_score(),
+            self.score,
+            side
+        )
+    }
+}
+
+// MAX_PHASE in the opening, falling to 0 as chariots, horses and cannons
+// are exchanged
+pub fn game_phase(board: &Board) -> i32 {
+    let phase: i32 = board
+        .squares
+        .iter()
+        .flatten()
+        .filter_map(|square| match square.piece {
+            Some((_, Piece::Chariot)) => Some(CHARIOT_PHASE),
+            Some((_, Piece::Horse)) => Some(HORSE_PHASE),
+            Some((_, Piece::Cannon)) => Some(CANNON_PHASE),
+            _ => None,
+        })
+        .sum();
+    phase.min(MAX_PHASE)
+}
+
+fn piece_params(params: &EvalParams, piece: Piece) -> &PieceParams {
+    match piece {
+        Piece::Soldier => &params.soldier,
+        Piece::Cannon => &params.cannon,
+        Piece::Horse => &params.horse,
+        Piece::Elephant => &params.elephant,
+        Piece::Advisor => &params.advisor,
+        Piece::Chariot => &params.chariot,
+        Piece::General => &params.general,
+    }
+}
+
+fn piece_params_mut(params: &mut EvalParams, piece: Piece) -> &mut PieceParams {
+    match piece {
+        Piece::Soldier => &mut params.soldier,
+        Piece::Cannon => &mut params.cannon,
+        Piece::Horse => &mut params.horse,
+        Piece::Elephant => &mut params.elephant,
+        Piece::Advisor => &mut params.advisor,
+        Piece::Chariot => &mut params.chariot,
+        Piece::General => &mut params.general,
+    }
+}
+
+fn side_index(color: Color) -> usize {
+    match color {
+        Color::Red => 0,
+        Color::Black => 1,
+    }
+}
+
+// Squares the piece on `pos` can move to without landing where a cheaper
+// enemy piece attacks; for a cannon only its non-capturing moves count
+fn safe_moves(board: &Board, attacks: &AttackMaps, pos: (usize, usize)) -> usize {
+    let Some((color, piece)) = board.squares[pos.0][pos.1].piece else {
+        return 0;
     };
-    
-    safety_score - exposed_penalty
+    let mut count = 0;
+    for_each_piece_square(board, pos, |to, can_move, attacks_square| {
+        // a cannon's moves that are not captures are the ones it does not attack
+        let counts = can_move && (piece != Piece::Cannon || !attacks_square);
+        if counts && !attacks.is_attacked_by_cheaper(to, color.opponent(), piece) {
+            count += 1;
+        }
+    });
+    count
 }
 
-fn find_general_files(board: &Board) -> (Option<usize>, Option<usize>) {
-    let mut red_general_file = None;
-    let mut black_general_file = None;
+// Generals facing each other with a single piece between them: that piece
+// is pinned to the file, which costs its owner
+fn pinned_screen_owner(board: &Board) -> Option<Color> {
+    let (red, black) = (board.find_general(Color::Red)?, board.find_general(Color::Black)?);
+    if red.1 != black.1 {
+        return None;
+    }
+    let screens: Vec<Color> = (black.0 + 1..red.0)
+        .filter_map(|rank| board.squares[rank][red.1].piece.map(|(color, _)| color))
+        .collect();
+    match screens[..] {
+        [owner] => Some(owner),
+        _ => None,
+    }
+}
 
+// The king safety counts of `color`, as listed in KING_SAFETY_NAMES
+fn king_safety_counts(
+    board: &Board,
+    attacks: &AttackMaps,
+    king_pos: (usize, usize),
+    color: Color,
+) -> [i32; KING_SAFETY_TERMS] {
+    let enemy = color.opponent();
+    let palace_ranks = if color == Color::Red { 7..10 } else { 0..3 };
+    let in_palace = |(rank, file): (usize, usize)| palace_ranks.contains(&rank) && (3..6).contains(&file);
+
+    let mut protectors = 0;
+    let mut advisors = 0;
+    let mut enemy_chariots = 0;
+    let mut enemy_horses = Vec::new();
     for rank in 0..10 {
         for file in 0..9 {
-            if let Some((color, Piece::General)) = board.squares[rank][file].piece {
-                if color == Color::Red {
-                    red_general_file = Some(file);
-                } else {
-                    black_general_file = Some(file);
+            match board.squares[rank][file].piece {
+                Some((owner, piece)) if owner == color => {
+                    if matches!(piece, Piece::Advisor | Piece::Elephant) && in_palace((rank, file)) {
+                        protectors += 1;
+                    }
+                    if piece == Piece::Advisor {
+                        advisors += 1;
+                    }
                 }
+                Some((_, Piece::Chariot)) => enemy_chariots += 1,
+                Some((_, Piece::Horse)) => enemy_horses.push((rank, file)),
+                _ => {}
             }
         }
     }
 
-    (red_general_file, black_general_file)
+    // Enemy pieces attacking a palace square, as masks of their squares
+    let hitting = palace_ranks
+        .clone()
+        .flat_map(|rank| (3..6).map(move |file| (rank, file)))
+        .fold(0, |mask, square| mask | attacks.attackers(square, enemy));
+    let mut attackers = 0;
+    let mut horses = 0;
+    let mut rest = hitting;
+    while rest != 0 {
+        let square = first_square(rest);
+        rest &= rest - 1;
+        match board.squares[square.0][square.1].piece {
+            Some((_, Piece::Horse)) => horses |= square_bit(square),
+            Some((_, Piece::Chariot | Piece::Cannon | Piece::Soldier)) => attackers += 1,
+            _ => {}
+        }
+    }
+
+    // Horses also count when one move takes them to a square attacking the
+    // palace; they move to an empty square or take a defender's piece
+    for &horse in &enemy_horses {
+        if horses & square_bit(horse) != 0 {
+            continue;
+        }
+        let mut moved = board.clone();
+        let reaches_palace = generate_piece_moves(board, horse).into_iter().any(|mv| {
+            let taken = moved.squares[mv.to.0][mv.to.1].piece;
+            moved.squares[mv.to.0][mv.to.1].piece = moved.squares[horse.0][horse.1].piece.take();
+            let mut hits = false;
+            piece_attacks(&moved, mv.to, |target| hits |= in_palace(target));
+            moved.squares[horse.0][horse.1].piece = moved.squares[mv.to.0][mv.to.1].piece.take();
+            moved.squares[mv.to.0][mv.to.1].piece = taken;
+            hits
+        });
+        if reaches_palace {
+            horses |= square_bit(horse);
+        }
+    }
+    let horses = horses.count_ones() as i32;
+
+    // Enemy cannons on the general's file, with the pieces in between
+    let (king_rank, king_file) = king_pos;
+    let mut central_cannons = 0;
+    let mut empty_headed_cannons = 0;
+    for step in [1, -1] {
+        let mut rank = king_rank as i32 + step;
+        let mut screens = 0;
+        while (0..10).contains(&rank) {
+            match board.squares[rank as usize][king_file].piece {
+                Some((owner, Piece::Cannon)) if owner == enemy => {
+                    // behind two screens it attacks nothing
+                    match screens {
+                        0 => empty_headed_cannons += 1,
+                        1 => central_cannons += 1,
+                        _ => {}
+                    }
+                    screens += 1;
+                }
+                Some(_) => screens += 1,
+                None => {}
+            }
+            rank += step;
+        }
+    }
+
+    // With an empty-headed cannon in front, a chariot covering the squares
+    // beside the general leaves it nowhere to go
+    let side_held = [king_file.wrapping_sub(1), king_file + 1]
+        .into_iter()
+        .filter(|&file| in_palace((king_rank, file)))
+        .any(|file| attacks.is_attacked_by((king_rank, file), enemy, Piece::Chariot));
+    let iron_bolts = if empty_headed_cannons > 0 && side_held { 1 } else { 0 };
+
+    // Palace files without any piece of the defender but the general
+    let open_files = (3..6)
+        .filter(|&file| {
+            (0..10).all(|rank| match board.squares[rank][file].piece {
+                Some((owner, piece)) => owner != color || piece == Piece::General,
+                None => true,
+            })
+        })
+        .count() as i32;
+
+    let ranks_forward = if color == Color::Red { 9 - king_rank } else { king_rank };
+    [
+        protectors,
+        -(ranks_forward as i32),
+        -attackers,
+        -horses,
+        -central_cannons,
+        -empty_headed_cannons,
+        -iron_bolts,
+        -open_files * enemy_chariots,
+        -(2 - advisors).max(0) * enemy_chariots,
+    ]
 }
@@ -0,0 +1,119 @@
+// A game record: tag pairs, a start position and a tree of moves with
+// comments and variations. Readers for the different file formats (PGN,
+// XQF) all produce this structure.
+use crate::board::Board;
+use crate::moves::Move;
+
+pub const ROOT: usize = 0;
+
+#[derive(Clone, Debug)]
+pub struct GameNode {
+    pub mv: Option<Move>, // None only for the root
+    pub comment: Option<String>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>, // the first child continues the main line
+}
+
+#[derive(Clone)]
+pub struct Game {
+    pub headers: Vec<(String, String)>,
+    pub start: Board,
+    pub nodes: Vec<GameNode>,
+}
+
+impl Game {
+    pub fn new(start: Board) -> Self {
+        Game {
+            headers: Vec::new(),
+            start,
+            nodes: vec![GameNode {
+                mv: None,
+                comment: None,
+                parent: None,
+                children: Vec::new(),
+            }],
+        }
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        match self.headers.iter_mut().find(|(key, _)| key.eq_ignore_ascii_case(name)) {
+            Some(entry) => entry.1 = value.to_string(),
+            None => self.headers.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    // Adds `mv` after `parent` and returns its node. A move that is already
+    // there is reused, so replaying the same line twice does not branch.
+    // The caller is responsible for the move being legal.
+    pub fn add_move(&mut self, parent: usize, mv: Move) -> usize {
+        if let Some(&existing) = self.nodes[parent]
+            .children
+            .iter()
+            .find(|&&child| self.nodes[child].mv.as_ref() == Some(&mv))
+        {
+            return existing;
+        }
+        let node = self.nodes.len();
+        self.nodes.push(GameNode {
+            mv: Some(mv),
+            comment: None,
+            parent: Some(parent),
+            children: Vec::new(),
+        });
+        self.nodes[parent].children.push(node);
+        node
+    }
+
+    // Nodes of the main line, not including the root
+    pub fn mainline(&self) -> Vec<usize> {
+        let mut line = Vec::new();
+        let mut node = ROOT;
+        while let Some(&next) = self.nodes[node].children.first() {
+            line.push(next);
+            node = next;
+        }
+        line
+    }
+
+    pub fn mainline_moves(&self) -> Vec<Move> {
+        self.mainline()
+            .into_iter()
+            .filter_map(|node| self.nodes[node].mv.clone())
+            .collect()
+    }
+
+    // Nodes from the first move down to `node`
+    pub fn path_to(&self, node: usize) -> Vec<usize> {
+        let mut path = Vec::new();
+        let mut current = node;
+        while let Some(parent) = self.nodes[current].parent {
+            path.push(current);
+            current = parent;
+        }
+        path.reverse();
+        path
+    }
+
+    // The position after the move at `node`
+    pub fn board_at(&self, node: usize) -> Board {
+        let mut board = self.start.clone();
+        for step in self.path_to(node) {
+            if let Some(mv) = &self.nodes[step].mv {
+                board.make_move(mv.from, mv.to);
+            }
+        }
+        board
+    }
+
+    // Plies from the start position to `node`
+    pub fn ply(&self, node: usize) -> usize {
+        self.path_to(node).len()
+    }
+}
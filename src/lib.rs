@@ -0,0 +1,26 @@
+// Xiangqi engine library: board representation, move generation,
+// evaluation and search. The `chess_engine` binary wraps it in a UCI loop;
+// other programs can embed it through the re-exports below.
+
+pub mod attacks;
+pub mod board;
+pub mod book;
+pub mod eval_params;
+pub mod evaluation;
+pub mod game;
+pub mod moves;
+pub mod notation;
+pub mod pgn;
+pub mod search;
+pub mod tt;
+pub mod tuner;
+pub mod xqf;
+
+pub use board::{Board, Color, Piece};
+pub use eval_params::EvalParams;
+pub use evaluation::{evaluate_position, evaluate_terms, game_phase, trace_evaluation, EvalTrace, TermScore};
+pub use game::Game;
+pub use moves::{generate_legal_moves, has_legal_move, Move};
+pub use search::{
+    find_best_move, IterationReport, ScoreBound, SearchLimits, SearchObserver, SearchResult, Searcher,
+};
@@ -0,0 +1,118 @@
+// Output for the protocol front end. stdout is reserved for protocol
+// responses, so anything meant for a human goes to stderr, or to the GUI as
+// `info string` once it has sent `debug on`. When a debug log file is open,
+// every line in either direction is also appended there with a timestamp.
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static DEBUG: AtomicBool = AtomicBool::new(false);
+static LOG_FILE: Mutex<Option<File>> = Mutex::new(None);
+
+pub fn set_debug(enabled: bool) {
+    DEBUG.store(enabled, Ordering::Relaxed);
+}
+
+// Opens `path` for appending; an empty path closes the current log
+pub fn set_log_file(path: &str) -> Result<(), String> {
+    let file = if path.is_empty() || path == "<empty>" {
+        None
+    } else {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("cannot open log file {}: {}", path, e))?;
+        Some(file)
+    };
+    *LOG_FILE.lock().unwrap() = file;
+    Ok(())
+}
+
+// Records a line received from the GUI
+pub fn log_input(line: &str) {
+    record("<<", line);
+}
+
+// Records a note that is not part of the protocol traffic, e.g. a search summary
+pub fn log_note(line: &str) {
+    record("##", line);
+}
+
+pub fn write_out(message: &str) {
+    let mut stdout = io::stdout().lock();
+    for line in message.lines() {
+        writeln!(stdout, "{}", line).unwrap();
+        record(">>", line);
+    }
+    stdout.flush().unwrap();
+}
+
+pub fn write_diag(message: &str) {
+    if DEBUG.load(Ordering::Relaxed) {
+        let lines: Vec<String> = message.lines().map(|line| format!("info string {}", line)).collect();
+        write_out(&lines.join("\n"));
+    } else {
+        eprintln!("{}", message);
+        for line in message.lines() {
+            record("!!", line);
+        }
+    }
+}
+
+fn record(direction: &str, line: &str) {
+    if let Some(file) = LOG_FILE.lock().unwrap().as_mut() {
+        // A failing log must never take the engine down mid-game
+        let _ = writeln!(file, "{} {} {}", timestamp(), direction, line);
+    }
+}
+
+// UTC wall-clock time as `YYYY-MM-DD hh:mm:ss.mmm`
+fn timestamp() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = now.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let time_of_day = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        time_of_day / 60 % 60,
+        time_of_day % 60,
+        now.subsec_millis()
+    )
+}
+
+// Days since 1970-01-01 to a proleptic Gregorian date
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// Formats like println! but routes through write_out
+macro_rules! send {
+    ($($arg:tt)*) => {
+        $crate::log::write_out(&format!($($arg)*))
+    };
+}
+pub(crate) use send;
+
+// Formats like println! but routes through write_diag
+macro_rules! diag {
+    ($($arg:tt)*) => {
+        $crate::log::write_diag(&format!($($arg)*))
+    };
+}
+pub(crate) use diag;
@@ -1,27 +1,57 @@
-mod board;
-mod moves;
-mod evaluation;
-mod search;
+mod bench;
+mod build_book;
+mod log;
+mod play;
+mod tune;
 mod uci;
+mod xboard;
 
-use uci::UCIEngine;
-use std::io::{self, Write};
+use log::diag;
+use uci::{Handoff, UCIEngine};
+use xboard::XBoardEngine;
 
 fn main() {
-    
-    println!("XiangqiEngine starting up...");
-    println!("Type 'uci' to initialize the engine");
-    println!("Available commands:");
-    println!("  uci      - Initialize the engine");
-    println!("  isready   - Check if engine is ready");
-    println!("  position  - Set up a position");
-    println!("  go        - Start calculating");
-    println!("  quit      - Exit the engine");
-    
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("bench") {
+        let (depth, threads, hash_mb) = bench::parse_bench_args(&args[1..]);
+        bench::run_bench(depth, threads, hash_mb, false);
+        return;
+    }
+    if args.first().map(String::as_str) == Some("play") {
+        match play::parse_play_args(&args[1..]) {
+            Ok((human, movetime_ms)) => play::run_play(human, movetime_ms),
+            Err(e) => {
+                diag!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if args.first().map(String::as_str) == Some("book") {
+        if args.get(1).map(String::as_str) != Some("build") {
+            diag!("Usage: book build [-o file] [--depth plies] [--min-count n] [--min-score percent] <games...>");
+            std::process::exit(1);
+        }
+        if let Err(e) = build_book::parse_build_args(&args[2..]).and_then(build_book::run_build) {
+            diag!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args.first().map(String::as_str) == Some("tune") {
+        if let Err(e) = tune::parse_tune_args(&args[1..]).and_then(tune::run_tune) {
+            diag!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    diag!("XiangqiEngine starting up, type 'uci' or 'xboard' to initialize the engine");
+
     let mut engine = UCIEngine::new();
-    
-    
-    io::stdout().flush().unwrap();
-    
-    engine.main_loop();
-}
\ No newline at end of file
+    if engine.main_loop() == Handoff::XBoard {
+        XBoardEngine::new().main_loop();
+    }
+}
@@ -23,6 +23,32 @@ impl Move {
             (b'9' - self.to.0 as u8) as char,
         )
     }
+
+    // parse a move in the format produced by `to_uci`, e.g. "h2e2"
+    pub fn from_uci(s: &str) -> Option<Move> {
+        let bytes = s.as_bytes();
+        if bytes.len() != 4 {
+            return None;
+        }
+        let square = |file: u8, rank: u8| -> Option<(usize, usize)> {
+            if (b'a'..=b'i').contains(&file) && rank.is_ascii_digit() {
+                Some(((b'9' - rank) as usize, (file - b'a') as usize))
+            } else {
+                None
+            }
+        };
+        Some(Move::new(square(bytes[0], bytes[1])?, square(bytes[2], bytes[3])?))
+    }
+
+    // The move in the position given by Board::mirror_files
+    pub fn mirror_files(&self) -> Move {
+        Move::new((self.from.0, 8 - self.from.1), (self.to.0, 8 - self.to.1))
+    }
+
+    // The move in the position given by Board::flip_colors
+    pub fn flip_colors(&self) -> Move {
+        Move::new((9 - self.from.0, self.from.1), (9 - self.to.0, self.to.1))
+    }
 }
 
 impl fmt::Display for Move {
@@ -31,6 +57,14 @@ impl fmt::Display for Move {
     }
 }
 
+// true if the side to move has a move that also passes make_move's checks;
+// in Xiangqi a side without one has lost, whether mated or stalemated
+pub fn has_legal_move(board: &Board) -> bool {
+    generate_legal_moves(board)
+        .iter()
+        .any(|mv| board.clone().make_move(mv.from, mv.to))
+}
+
 pub fn generate_legal_moves(board: &Board) -> Vec<Move> {
     let mut moves = Vec::new();
     let color = if board.red_to_move { Color::Red } else { Color::Black };
@@ -50,23 +84,56 @@ pub fn generate_legal_moves(board: &Board) -> Vec<Move> {
     moves
 }
 
-fn generate_piece_moves(board: &Board, pos: (usize, usize)) -> Vec<Move> {
+// Pseudo-legal moves of the piece on `pos`, as generate_legal_moves gives them
+pub fn generate_piece_moves(board: &Board, pos: (usize, usize)) -> Vec<Move> {
     let mut moves = Vec::new();
+    for_each_piece_square(board, pos, |to, can_move, _| {
+        if can_move {
+            moves.push(Move::new(pos, to));
+        }
+    });
+    moves
+}
+
+// Calls `visit` with every square the piece on `pos` attacks, whoever stands
+// there: the squares it could capture on if an enemy piece did. These are the
+// same rules the moves come from, so the evaluation's attack maps agree with
+// move generation.
+pub fn piece_attacks(board: &Board, pos: (usize, usize), mut visit: impl FnMut((usize, usize))) {
+    for_each_piece_square(board, pos, |to, _, attacks| {
+        if attacks {
+            visit(to);
+        }
+    });
+}
+
+// Calls `visit(square, can_move, attacks)` for every square the piece on
+// `pos` reaches. The two flags only differ on squares held by its own side,
+// which it defends but cannot move to, and for cannons, which move along
+// empty squares but attack only behind a screen.
+pub fn for_each_piece_square(board: &Board, pos: (usize, usize), mut visit: impl FnMut((usize, usize), bool, bool)) {
     if let Some((color, piece_type)) = board.squares[pos.0][pos.1].piece {
+        let visit = &mut visit;
         match piece_type {
-            Piece::General => generate_general_moves(board, pos, color, &mut moves),
-            Piece::Advisor => generate_advisor_moves(board, pos, color, &mut moves),
-            Piece::Elephant => generate_elephant_moves(board, pos, color, &mut moves),
-            Piece::Horse => generate_horse_moves(board, pos, color, &mut moves),
-            Piece::Chariot => generate_chariot_moves(board, pos, color, &mut moves),
-            Piece::Cannon => generate_cannon_moves(board, pos, color, &mut moves),
-            Piece::Soldier => generate_soldier_moves(board, pos, color, &mut moves),
+            Piece::General => generate_general_moves(board, pos, color, visit),
+            Piece::Advisor => generate_advisor_moves(board, pos, color, visit),
+            Piece::Elephant => generate_elephant_moves(board, pos, color, visit),
+            Piece::Horse => generate_horse_moves(board, pos, color, visit),
+            Piece::Chariot => generate_chariot_moves(board, pos, color, visit),
+            Piece::Cannon => generate_cannon_moves(board, pos, color, visit),
+            Piece::Soldier => generate_soldier_moves(board, pos, color, visit),
         }
     }
-    moves
 }
 
-fn generate_chariot_moves(board: &Board, pos: (usize, usize), color: Color, moves: &mut Vec<Move>) {
+// Reports a square a piece steps or slides onto: it attacks it either way,
+// and may move there unless its own side holds it
+fn visit_square(board: &Board, to: (usize, usize), color: Color, visit: &mut impl FnMut((usize, usize), bool, bool)) {
+    let own = matches!(board.squares[to.0][to.1].piece, Some((piece_color, _)) if piece_color == color);
+    visit(to, !own, true);
+}
+
+fn generate_chariot_moves(board: &Board, pos: (usize, usize), color: Color, visit: &mut impl FnMut((usize, usize), bool, bool)) {
     let directions = [(0, 1), (1, 0), (0, -1), (-1, 0)];
     for &(dx, dy) in &directions {
         let mut x = pos.0 as i32;
@@ -74,24 +141,19 @@ fn generate_chariot_moves(board: &Board, pos: (usize, usize), color: Color, move
         loop {
             x += dx;
             y += dy;
-            if x < 0 || x >= 10 || y < 0 || y >= 9 {
+            if !(0..10).contains(&x) || !(0..9).contains(&y) {
                 break;
             }
             let new_pos = (x as usize, y as usize);
-            match board.squares[new_pos.0][new_pos.1].piece {
-                None => moves.push(Move::new(pos, new_pos)),
-                Some((piece_color, _)) => {
-                    if piece_color != color {
-                        moves.push(Move::new(pos, new_pos));
-                    }
-                    break;
-                }
+            visit_square(board, new_pos, color, visit);
+            if board.squares[new_pos.0][new_pos.1].piece.is_some() {
+                break;
             }
         }
     }
 }
 
-fn generate_horse_moves(board: &Board, pos: (usize, usize), color: Color, moves: &mut Vec<Move>) {
+fn generate_horse_moves(board: &Board, pos: (usize, usize), color: Color, visit: &mut impl FnMut((usize, usize), bool, bool)) {
     let (rank, file) = pos;
     let possible_moves = [
         // only add moves if the starting position allows them
@@ -101,20 +163,13 @@ fn generate_horse_moves(board: &Board, pos: (usize, usize), color: Color, moves:
         (if rank + 2 <= 9 && file >= 1 { Some((rank + 2, file - 1)) } else { None }),
         (if rank >= 1 && file <= 6 { Some((rank - 1, file + 2)) } else { None }),
         (if rank >= 1 && file >= 2 { Some((rank - 1, file - 2)) } else { None }),
-        (if rank + 1 <= 9 && file <= 6 { Some((rank + 1, file + 2)) } else { None }),
-        (if rank + 1 <= 9 && file >= 2 { Some((rank + 1, file - 2)) } else { None }),
+        (if rank < 9 && file <= 6 { Some((rank + 1, file + 2)) } else { None }),
+        (if rank < 9 && file >= 2 { Some((rank + 1, file - 2)) } else { None }),
     ];
 
     for possible_move in possible_moves.iter().flatten() {
-        let (new_rank, new_file) = *possible_move;
-        if !is_horse_blocked(board, pos, (new_rank, new_file)) {
-            if let Some((piece_color, _)) = board.squares[new_rank][new_file].piece {
-                if piece_color != color {
-                    moves.push(Move::new(pos, (new_rank, new_file)));
-                }
-            } else {
-                moves.push(Move::new(pos, (new_rank, new_file)));
-            }
+        if !is_horse_blocked(board, pos, *possible_move) {
+            visit_square(board, *possible_move, color, visit);
         }
     }
 }
@@ -159,7 +214,7 @@ fn is_horse_blocked(board: &Board, from: (usize, usize), to: (usize, usize)) ->
     board.squares[blocking_pos.0][blocking_pos.1].piece.is_some()
 }
 
-fn generate_cannon_moves(board: &Board, pos: (usize, usize), color: Color, moves: &mut Vec<Move>) {
+fn generate_cannon_moves(board: &Board, pos: (usize, usize), color: Color, visit: &mut impl FnMut((usize, usize), bool, bool)) {
     let directions = [(0, 1), (1, 0), (0, -1), (-1, 0)];
     for &(dx, dy) in &directions {
         let mut x = pos.0 as i32;
@@ -169,30 +224,33 @@ fn generate_cannon_moves(board: &Board, pos: (usize, usize), color: Color, moves
         loop {
             x += dx;
             y += dy;
-            if x < 0 || x >= 10 || y < 0 || y >= 9 {
+            if !(0..10).contains(&x) || !(0..9).contains(&y) {
                 break;
             }
             let new_pos = (x as usize, y as usize);
             
             if !platform_found {
                 if board.squares[new_pos.0][new_pos.1].piece.is_none() {
-                    moves.push(Move::new(pos, new_pos));
+                    visit(new_pos, true, false);
                 } else {
                     platform_found = true;
                 }
             } else {
-                if let Some((piece_color, _)) = board.squares[new_pos.0][new_pos.1].piece {
-                    if piece_color != color {
-                        moves.push(Move::new(pos, new_pos));
+                // behind the screen the cannon attacks every square up to the
+                // next piece, but only moves there to capture
+                match board.squares[new_pos.0][new_pos.1].piece {
+                    None => visit(new_pos, false, true),
+                    Some((piece_color, _)) => {
+                        visit(new_pos, piece_color != color, true);
+                        break;
                     }
-                    break;
                 }
             }
         }
     }
 }
 
-fn generate_general_moves(board: &Board, pos: (usize, usize), color: Color, moves: &mut Vec<Move>) {
+fn generate_general_moves(board: &Board, pos: (usize, usize), color: Color, visit: &mut impl FnMut((usize, usize), bool, bool)) {
     let (rank, file) = pos;
     let palace_moves = match color {
         Color::Red => [(7, 3), (7, 4), (7, 5), (8, 3), (8, 4), (8, 5), (9, 3), (9, 4), (9, 5)],
@@ -201,18 +259,12 @@ fn generate_general_moves(board: &Board, pos: (usize, usize), color: Color, move
 
     for &(new_rank, new_file) in &palace_moves {
         if (new_rank as i32 - rank as i32).abs() + (new_file as i32 - file as i32).abs() == 1 {
-            if let Some((piece_color, _)) = board.squares[new_rank][new_file].piece {
-                if piece_color != color {
-                    moves.push(Move::new(pos, (new_rank, new_file)));
-                }
-            } else {
-                moves.push(Move::new(pos, (new_rank, new_file)));
-            }
+            visit_square(board, (new_rank, new_file), color, visit);
         }
     }
 }
 
-fn generate_advisor_moves(board: &Board, pos: (usize, usize), color: Color, moves: &mut Vec<Move>) {
+fn generate_advisor_moves(board: &Board, pos: (usize, usize), color: Color, visit: &mut impl FnMut((usize, usize), bool, bool)) {
     let (rank, file) = pos;
     let palace_moves = match color {
         Color::Red => [(7, 3), (7, 5), (8, 4), (9, 3), (9, 5)],
@@ -221,43 +273,37 @@ fn generate_advisor_moves(board: &Board, pos: (usize, usize), color: Color, move
 
     for &(new_rank, new_file) in &palace_moves {
         if (new_rank as i32 - rank as i32).abs() == 1 && (new_file as i32 - file as i32).abs() == 1 {
-            if let Some((piece_color, _)) = board.squares[new_rank][new_file].piece {
-                if piece_color != color {
-                    moves.push(Move::new(pos, (new_rank, new_file)));
-                }
-            } else {
-                moves.push(Move::new(pos, (new_rank, new_file)));
-            }
+            visit_square(board, (new_rank, new_file), color, visit);
         }
     }
 }
 
-fn generate_elephant_moves(board: &Board, pos: (usize, usize), color: Color, moves: &mut Vec<Move>) {
+fn generate_elephant_moves(board: &Board, pos: (usize, usize), color: Color, visit: &mut impl FnMut((usize, usize), bool, bool)) {
     let (rank, file) = pos;
     
     // check each possible diagonal move if it's within bounds
     // forward-right diagonal
     if rank + 2 <= 9 && file + 2 <= 8 {
-        add_elephant_move(board, pos, (rank + 2, file + 2), color, moves);
+        add_elephant_move(board, pos, (rank + 2, file + 2), color, visit);
     }
     
     // forward-left diagonal
     if rank + 2 <= 9 && file >= 2 {
-        add_elephant_move(board, pos, (rank + 2, file - 2), color, moves);
+        add_elephant_move(board, pos, (rank + 2, file - 2), color, visit);
     }
     
     // backward-right diagonal
     if rank >= 2 && file + 2 <= 8 {
-        add_elephant_move(board, pos, (rank - 2, file + 2), color, moves);
+        add_elephant_move(board, pos, (rank - 2, file + 2), color, visit);
     }
     
     // backward-left diagonal
     if rank >= 2 && file >= 2 {
-        add_elephant_move(board, pos, (rank - 2, file - 2), color, moves);
+        add_elephant_move(board, pos, (rank - 2, file - 2), color, visit);
     }
 }
 
-fn add_elephant_move(board: &Board, pos: (usize, usize), new_pos: (usize, usize), color: Color, moves: &mut Vec<Move>) {
+fn add_elephant_move(board: &Board, pos: (usize, usize), new_pos: (usize, usize), color: Color, visit: &mut impl FnMut((usize, usize), bool, bool)) {
     let (rank, file) = pos;
     let (new_rank, new_file) = new_pos;
     
@@ -267,18 +313,12 @@ fn add_elephant_move(board: &Board, pos: (usize, usize), new_pos: (usize, usize)
         let eye_rank = (rank + new_rank) / 2;
         let eye_file = (file + new_file) / 2;
         if board.squares[eye_rank][eye_file].piece.is_none() {
-            if let Some((piece_color, _)) = board.squares[new_rank][new_file].piece {
-                if piece_color != color {
-                    moves.push(Move::new(pos, (new_rank, new_file)));
-                }
-            } else {
-                moves.push(Move::new(pos, (new_rank, new_file)));
-            }
+            visit_square(board, new_pos, color, visit);
         }
     }
 }
 
-fn generate_soldier_moves(board: &Board, pos: (usize, usize), color: Color, moves: &mut Vec<Move>) {
+fn generate_soldier_moves(board: &Board, pos: (usize, usize), color: Color, visit: &mut impl FnMut((usize, usize), bool, bool)) {
     let (rank, file) = pos;
     let mut possible_moves = Vec::new();
 
@@ -300,12 +340,6 @@ fn generate_soldier_moves(board: &Board, pos: (usize, usize), color: Color, move
     }
 
     for &(new_rank, new_file) in &possible_moves {
-        if let Some((piece_color, _)) = board.squares[new_rank][new_file].piece {
-            if piece_color != color {
-                moves.push(Move::new(pos, (new_rank, new_file)));
-            }
-        } else {
-            moves.push(Move::new(pos, (new_rank, new_file)));
-        }
+        visit_square(board, (new_rank, new_file), color, visit);
     }
-}
\ No newline at end of file
+}
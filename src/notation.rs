@@ -0,0 +1,423 @@
+// Human move notations. Besides ICCS coordinates (h2e2, H2-E2) we read and
+// write WXF (C2=5, H8+7, C+=5) and traditional Chinese (炮二平五, 马8进7,
+// 前车进一). Both describe a move relative to the mover: files are numbered
+// 1-9 from the mover's own right, and "advance" means towards the enemy.
+//
+// Identical pieces sharing a file are told apart by their order from the
+// front instead of the file: 前/后 (WXF +/-) for two, 前/中/后 for three and
+// 一二三四五 (WXF 1-5 before the letter) for more. When soldiers are stacked
+// on two files at once, the file number replaces the piece name (前七进一,
+// WXF +7+1). Advisors and elephants never need this, since the direction of
+// the move already tells two of them apart.
+use crate::board::{Board, Color, Piece};
+use crate::moves::{generate_legal_moves, Move};
+
+const CHINESE_NUMERALS: [char; 9] = ['一', '二', '三', '四', '五', '六', '七', '八', '九'];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Action {
+    Advance,
+    Retreat,
+    Traverse,
+}
+
+// Position of a piece among identical pieces on one file
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Tandem {
+    Front,
+    Rear,
+    Nth(usize), // 1-based, counted from the front
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Origin {
+    File(usize),
+    Tandem { position: Tandem, count: usize, file: Option<usize> },
+}
+
+struct Descriptor {
+    piece: Piece,
+    origin: Origin,
+    action: Action,
+    target: usize,
+}
+
+// Parses a move in ICCS, WXF or Chinese notation for the side to move.
+// The result is always a legal move on `board`.
+pub fn parse_move(board: &Board, text: &str) -> Result<Move, String> {
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let iccs = text.replace('-', "").to_ascii_lowercase();
+    if let Some(mv) = Move::from_uci(&iccs) {
+        return if legal_moves(board).contains(&mv) {
+            Ok(mv)
+        } else {
+            Err(format!("Illegal move: {}", text))
+        };
+    }
+
+    if text.is_ascii() {
+        parse_wxf(board, &text)
+    } else {
+        parse_chinese(board, &text)
+    }
+}
+
+pub fn parse_wxf(board: &Board, text: &str) -> Result<Move, String> {
+    let descriptor = read_wxf(text).ok_or_else(|| format!("Invalid WXF move: {}", text))?;
+    resolve(board, text, &descriptor)
+}
+
+pub fn parse_chinese(board: &Board, text: &str) -> Result<Move, String> {
+    let descriptor = read_chinese(text).ok_or_else(|| format!("Invalid Chinese move: {}", text))?;
+    resolve(board, text, &descriptor)
+}
+
+pub fn format_wxf(board: &Board, mv: &Move) -> Result<String, String> {
+    let descriptor = describe(board, mv)?;
+    let letter = wxf_letter(descriptor.piece);
+    let origin = match descriptor.origin {
+        Origin::File(number) => format!("{}{}", letter, number),
+        Origin::Tandem { position, file, .. } => {
+            let mark = match position {
+                Tandem::Front => '+',
+                Tandem::Rear => '-',
+                Tandem::Nth(n) => digit_char(n),
+            };
+            match (position, file) {
+                (_, Some(number)) => format!("{}{}", mark, number),
+                (Tandem::Nth(_), None) => format!("{}{}", mark, letter),
+                (_, None) => format!("{}{}", letter, mark),
+            }
+        }
+    };
+    let op = match descriptor.action {
+        Action::Advance => '+',
+        Action::Retreat => '-',
+        Action::Traverse => '=',
+    };
+    Ok(format!("{}{}{}", origin, op, descriptor.target))
+}
+
+pub fn format_chinese(board: &Board, mv: &Move) -> Result<String, String> {
+    let descriptor = describe(board, mv)?;
+    let color = piece_color(board, mv)?;
+    let number = |n: usize| match color {
+        Color::Red => CHINESE_NUMERALS[n - 1],
+        Color::Black => digit_char(n),
+    };
+    let name = chinese_name(color, descriptor.piece);
+    let origin = match descriptor.origin {
+        Origin::File(file) => format!("{}{}", name, number(file)),
+        Origin::Tandem { position, count, file } => {
+            let mark = match position {
+                Tandem::Front => '前',
+                Tandem::Rear => '后',
+                Tandem::Nth(2) if count == 3 => '中',
+                Tandem::Nth(n) => CHINESE_NUMERALS[n - 1],
+            };
+            match file {
+                Some(file) => format!("{}{}", mark, number(file)),
+                None => format!("{}{}", mark, name),
+            }
+        }
+    };
+    let action = match descriptor.action {
+        Action::Advance => '进',
+        Action::Retreat => '退',
+        Action::Traverse => '平',
+    };
+    Ok(format!("{}{}{}", origin, action, number(descriptor.target)))
+}
+
+// generate_legal_moves is pseudo-legal; keep only moves make_move accepts
+fn legal_moves(board: &Board) -> Vec<Move> {
+    generate_legal_moves(board)
+        .into_iter()
+        .filter(|mv| board.clone().make_move(mv.from, mv.to))
+        .collect()
+}
+
+fn piece_color(board: &Board, mv: &Move) -> Result<Color, String> {
+    match board.squares[mv.from.0][mv.from.1].piece {
+        Some((color, _)) => Ok(color),
+        None => Err(format!("No piece on the from-square of {}", mv)),
+    }
+}
+
+// File number 1-9 as seen by `color`
+fn file_number(color: Color, file: usize) -> usize {
+    match color {
+        Color::Red => 9 - file,
+        Color::Black => file + 1,
+    }
+}
+
+fn file_index(color: Color, number: usize) -> usize {
+    match color {
+        Color::Red => 9 - number,
+        Color::Black => number - 1,
+    }
+}
+
+// Ranks gained towards the enemy, negative for a retreat
+fn advance(color: Color, mv: &Move) -> i32 {
+    match color {
+        Color::Red => mv.from.0 as i32 - mv.to.0 as i32,
+        Color::Black => mv.to.0 as i32 - mv.from.0 as i32,
+    }
+}
+
+// Pieces moving along ranks and files give a distance when advancing or
+// retreating; the others give the file they land on
+fn moves_straight(piece: Piece) -> bool {
+    matches!(piece, Piece::General | Piece::Chariot | Piece::Cannon | Piece::Soldier)
+}
+
+// Squares holding `color`'s `piece` on `file`, front first
+fn stacked(board: &Board, color: Color, piece: Piece, file: usize) -> Vec<(usize, usize)> {
+    let mut squares: Vec<(usize, usize)> = (0..10)
+        .map(|rank| (rank, file))
+        .filter(|&(rank, file)| board.squares[rank][file].piece == Some((color, piece)))
+        .collect();
+    if color == Color::Black {
+        squares.reverse();
+    }
+    squares
+}
+
+fn describe(board: &Board, mv: &Move) -> Result<Descriptor, String> {
+    let (color, piece) = board.squares[mv.from.0][mv.from.1]
+        .piece
+        .ok_or_else(|| format!("No piece on the from-square of {}", mv))?;
+
+    let gained = advance(color, mv);
+    let (action, target) = if gained == 0 {
+        (Action::Traverse, file_number(color, mv.to.1))
+    } else {
+        let action = if gained > 0 { Action::Advance } else { Action::Retreat };
+        let target = if moves_straight(piece) {
+            gained.unsigned_abs() as usize
+        } else {
+            file_number(color, mv.to.1)
+        };
+        (action, target)
+    };
+
+    let on_file = stacked(board, color, piece, mv.from.1);
+    let origin = if on_file.len() < 2 || matches!(piece, Piece::Advisor | Piece::Elephant) {
+        Origin::File(file_number(color, mv.from.1))
+    } else {
+        let count = on_file.len();
+        let index = on_file.iter().position(|&square| square == mv.from).unwrap_or(0);
+        // with four or more on the file every piece is numbered
+        let position = if count > 3 {
+            Tandem::Nth(index + 1)
+        } else if index == 0 {
+            Tandem::Front
+        } else if index == count - 1 {
+            Tandem::Rear
+        } else {
+            Tandem::Nth(index + 1)
+        };
+        let other_stacks = (0..9)
+            .filter(|&file| file != mv.from.1)
+            .any(|file| stacked(board, color, piece, file).len() >= 2);
+        let file = other_stacks.then(|| file_number(color, mv.from.1));
+        Origin::Tandem { position, count, file }
+    };
+
+    Ok(Descriptor { piece, origin, action, target })
+}
+
+// Legal move matching the descriptor; more than one means the text was ambiguous
+fn resolve(board: &Board, text: &str, descriptor: &Descriptor) -> Result<Move, String> {
+    let color = if board.red_to_move { Color::Red } else { Color::Black };
+    let origins: Vec<(usize, usize)> = match descriptor.origin {
+        Origin::File(number) => stacked(board, color, descriptor.piece, file_index(color, number)),
+        Origin::Tandem { position, file, .. } => {
+            let files: Vec<usize> = match file {
+                Some(number) => vec![file_index(color, number)],
+                None => (0..9).collect(),
+            };
+            files
+                .into_iter()
+                .map(|file| stacked(board, color, descriptor.piece, file))
+                .filter(|squares| squares.len() >= 2)
+                .filter_map(|squares| match position {
+                    Tandem::Front => squares.first().copied(),
+                    Tandem::Rear => squares.last().copied(),
+                    Tandem::Nth(n) => squares.get(n - 1).copied(),
+                })
+                .collect()
+        }
+    };
+
+    let straight = moves_straight(descriptor.piece);
+    let matches: Vec<Move> = legal_moves(board)
+        .into_iter()
+        .filter(|mv| origins.contains(&mv.from))
+        .filter(|mv| {
+            let gained = advance(color, mv);
+            let lands = |ranks: i32| {
+                if straight {
+                    mv.to.1 == mv.from.1 && ranks == descriptor.target as i32
+                } else {
+                    mv.to.1 == file_index(color, descriptor.target)
+                }
+            };
+            match descriptor.action {
+                Action::Traverse => gained == 0 && mv.to.1 == file_index(color, descriptor.target),
+                Action::Advance => gained > 0 && lands(gained),
+                Action::Retreat => gained < 0 && lands(-gained),
+            }
+        })
+        .collect();
+
+    match matches.len() {
+        1 => Ok(matches[0].clone()),
+        0 => Err(format!("Illegal move: {}", text)),
+        _ => Err(format!("Ambiguous move: {}", text)),
+    }
+}
+
+// <piece><file or +/-><op><n>; <+/-/1-5><piece><op><n> for tandem pieces;
+// <+/-/1-5><file><op><n> for soldiers stacked on more than one file
+fn read_wxf(text: &str) -> Option<Descriptor> {
+    let chars: Vec<char> = text.to_ascii_uppercase().chars().collect();
+    if chars.len() != 4 {
+        return None;
+    }
+    let tandem = |c: char| match c {
+        '+' => Some(Tandem::Front),
+        '-' => Some(Tandem::Rear),
+        '1'..='5' => Some(Tandem::Nth(digit(c)?)),
+        _ => None,
+    };
+
+    let (piece, origin) = if let Some(piece) = wxf_piece(chars[0]) {
+        let origin = match chars[1] {
+            '+' | '-' => tandem_origin(tandem(chars[1])?, None),
+            c => Origin::File(digit(c)?),
+        };
+        (piece, origin)
+    } else {
+        let position = tandem(chars[0])?;
+        match wxf_piece(chars[1]) {
+            Some(piece) => (piece, tandem_origin(position, None)),
+            None => (Piece::Soldier, tandem_origin(position, Some(digit(chars[1])?))),
+        }
+    };
+    let action = match chars[2] {
+        '+' => Action::Advance,
+        '-' => Action::Retreat,
+        '=' | '.' => Action::Traverse,
+        _ => return None,
+    };
+    let target = digit(chars[3])?;
+    Some(Descriptor { piece, origin, action, target })
+}
+
+// <piece><file><action><n>; <前/中/后/一-五><piece><action><n> for tandem
+// pieces; <前/中/后/一-五><file><action><n> for soldiers on more than one file
+fn read_chinese(text: &str) -> Option<Descriptor> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() != 4 {
+        return None;
+    }
+    let tandem = match chars[0] {
+        '前' => Some(Tandem::Front),
+        '中' => Some(Tandem::Nth(2)),
+        '后' | '後' => Some(Tandem::Rear),
+        // a plain move starts with the piece, so a leading numeral is an order
+        c => digit(c).filter(|&n| n <= 5).map(Tandem::Nth),
+    };
+
+    let (piece, origin) = match tandem {
+        Some(position) => match chinese_piece(chars[1]) {
+            Some(piece) => (piece, tandem_origin(position, None)),
+            None => (Piece::Soldier, tandem_origin(position, Some(digit(chars[1])?))),
+        },
+        None => (chinese_piece(chars[0])?, Origin::File(digit(chars[1])?)),
+    };
+    let action = match chars[2] {
+        '进' | '進' => Action::Advance,
+        '退' => Action::Retreat,
+        '平' => Action::Traverse,
+        _ => return None,
+    };
+    let target = digit(chars[3])?;
+    Some(Descriptor { piece, origin, action, target })
+}
+
+// The stack size is only needed for formatting, so parsing leaves it open
+fn tandem_origin(position: Tandem, file: Option<usize>) -> Origin {
+    Origin::Tandem { position, count: 0, file }
+}
+
+fn wxf_piece(c: char) -> Option<Piece> {
+    match c {
+        'K' => Some(Piece::General),
+        'A' => Some(Piece::Advisor),
+        'E' | 'B' => Some(Piece::Elephant),
+        'H' | 'N' => Some(Piece::Horse),
+        'R' => Some(Piece::Chariot),
+        'C' => Some(Piece::Cannon),
+        'P' => Some(Piece::Soldier),
+        _ => None,
+    }
+}
+
+fn wxf_letter(piece: Piece) -> char {
+    match piece {
+        Piece::General => 'K',
+        Piece::Advisor => 'A',
+        Piece::Elephant => 'E',
+        Piece::Horse => 'H',
+        Piece::Chariot => 'R',
+        Piece::Cannon => 'C',
+        Piece::Soldier => 'P',
+    }
+}
+
+fn chinese_piece(c: char) -> Option<Piece> {
+    match c {
+        '帅' | '帥' | '将' | '將' => Some(Piece::General),
+        '仕' | '士' => Some(Piece::Advisor),
+        '相' | '象' => Some(Piece::Elephant),
+        '马' | '馬' | '傌' => Some(Piece::Horse),
+        '车' | '車' | '俥' => Some(Piece::Chariot),
+        '炮' | '砲' | '包' => Some(Piece::Cannon),
+        '兵' | '卒' => Some(Piece::Soldier),
+        _ => None,
+    }
+}
+
+fn chinese_name(color: Color, piece: Piece) -> char {
+    match (color, piece) {
+        (Color::Red, Piece::General) => '帅',
+        (Color::Black, Piece::General) => '将',
+        (Color::Red, Piece::Advisor) => '仕',
+        (Color::Black, Piece::Advisor) => '士',
+        (Color::Red, Piece::Elephant) => '相',
+        (Color::Black, Piece::Elephant) => '象',
+        (_, Piece::Horse) => '马',
+        (_, Piece::Chariot) => '车',
+        (_, Piece::Cannon) => '炮',
+        (Color::Red, Piece::Soldier) => '兵',
+        (Color::Black, Piece::Soldier) => '卒',
+    }
+}
+
+// 1-9 as an ASCII digit, a full-width digit or a Chinese numeral
+fn digit(c: char) -> Option<usize> {
+    let value = match c {
+        '1'..='9' => c as usize - '0' as usize,
+        '１'..='９' => c as usize - '１' as usize + 1,
+        _ => CHINESE_NUMERALS.iter().position(|&n| n == c)? + 1,
+    };
+    Some(value)
+}
+
+fn digit_char(n: usize) -> char {
+    char::from_digit(n as u32, 10).unwrap_or('?')
+}
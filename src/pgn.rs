@@ -0,0 +1,370 @@
+// Xiangqi PGN: tag pairs such as [Variant "xiangqi"] and [FEN "..."],
+// followed by movetext in ICCS, WXF or Chinese notation with {comments},
+// ; line comments, (variations), $NAGs and a result. Every move is checked
+// against the position it is played in.
+use crate::board::Board;
+use crate::game::{Game, ROOT};
+use crate::moves::Move;
+use crate::notation::{format_chinese, format_wxf, parse_move};
+
+const LINE_WIDTH: usize = 80;
+
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    VariationStart,
+    VariationEnd,
+    Result(String),
+    Move(String),
+}
+
+// Parses every game in `text`
+pub fn parse_pgn(text: &str) -> Result<Vec<Game>, String> {
+    parse_numbered(text, 1)
+}
+
+// Parses each game in `text` on its own, so that a damaged game does not
+// keep the others from being read
+pub fn parse_pgn_games(text: &str) -> Vec<Result<Game, String>> {
+    let mut results = Vec::new();
+    for game_text in split_games(text) {
+        match parse_numbered(game_text, results.len() + 1) {
+            Ok(games) => results.extend(games.into_iter().map(Ok)),
+            Err(e) => results.push(Err(e)),
+        }
+    }
+    results
+}
+
+// Games are numbered from `first` in error messages
+fn parse_numbered(text: &str, first: usize) -> Result<Vec<Game>, String> {
+    let mut games = Vec::new();
+    let mut headers: Vec<(String, String)> = Vec::new();
+    let mut builder: Option<Builder> = None;
+
+    for token in tokenize(text)? {
+        match token {
+            Token::Tag(name, value) => {
+                // tags after movetext belong to the next game
+                if let Some(finished) = builder.take() {
+                    games.push(finished.finish()?);
+                }
+                headers.push((name, value));
+            }
+            Token::Result(result) => {
+                let mut current = match builder.take() {
+                    Some(current) => current,
+                    None => Builder::new(std::mem::take(&mut headers), first + games.len())?,
+                };
+                if current.game.header("Result").is_none() {
+                    current.game.set_header("Result", &result);
+                }
+                games.push(current.finish()?);
+            }
+            token => {
+                if builder.is_none() {
+                    builder = Some(Builder::new(std::mem::take(&mut headers), first + games.len())?);
+                }
+                if let Some(current) = builder.as_mut() {
+                    current.apply(token)?;
+                }
+            }
+        }
+    }
+    if let Some(finished) = builder {
+        games.push(finished.finish()?);
+    } else if !headers.is_empty() {
+        games.push(Builder::new(headers, first + games.len())?.finish()?);
+    }
+    Ok(games)
+}
+
+// Splits a file into the text of each game. A game starts at the first tag
+// line after movetext.
+fn split_games(text: &str) -> Vec<&str> {
+    let mut games = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+    let mut in_movetext = false;
+    for line in text.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let line = line.trim();
+        if line.starts_with('[') {
+            if in_movetext {
+                games.push(&text[start..line_start]);
+                start = line_start;
+                in_movetext = false;
+            }
+        } else if !line.is_empty() {
+            in_movetext = true;
+        }
+    }
+    if !text[start..].trim().is_empty() {
+        games.push(&text[start..]);
+    }
+    games
+}
+
+// Writes one game, with moves in the notation named by its Format tag
+// (ICCS unless it says WXF or Chinese)
+pub fn write_pgn(game: &Game) -> String {
+    let mut out = String::new();
+    for (name, value) in &game.headers {
+        out.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+    }
+    let mut initial = Board::new();
+    initial.setup_initial_position();
+    if game.header("FEN").is_none() && game.start.to_fen() != initial.to_fen() {
+        out.push_str(&format!("[FEN \"{}\"]\n", game.start.to_fen()));
+    }
+    out.push('\n');
+
+    let format = game.header("Format").unwrap_or("ICCS").to_ascii_lowercase();
+    let mut tokens = Vec::new();
+    if let Some(comment) = &game.nodes[ROOT].comment {
+        tokens.push(format!("{{{}}}", comment.replace('}', ")")));
+    }
+    write_line(game, ROOT, &game.start, true, &format, &mut tokens);
+    tokens.push(game.header("Result").unwrap_or("*").to_string());
+
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.chars().count() + 1 + token.chars().count() > LINE_WIDTH {
+            out.push_str(&line);
+            out.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    out.push_str(&line);
+    out.push_str("\n\n");
+    out
+}
+
+struct Builder {
+    game: Game,
+    number: usize, // 1-based position of the game in the file, for errors
+    current: usize,
+    variations: Vec<usize>, // node to return to at the end of each open variation
+}
+
+impl Builder {
+    fn new(headers: Vec<(String, String)>, number: usize) -> Result<Self, String> {
+        let start = match headers.iter().find(|(name, _)| name.eq_ignore_ascii_case("FEN")) {
+            Some((_, fen)) => Board::from_fen(fen).map_err(|e| format!("Game {}: {}", number, e))?,
+            None => {
+                let mut board = Board::new();
+                board.setup_initial_position();
+                board
+            }
+        };
+        let mut game = Game::new(start);
+        game.headers = headers;
+        Ok(Builder {
+            game,
+            number,
+            current: ROOT,
+            variations: Vec::new(),
+        })
+    }
+
+    fn apply(&mut self, token: Token) -> Result<(), String> {
+        match token {
+            Token::Comment(text) => {
+                let comment = &mut self.game.nodes[self.current].comment;
+                *comment = Some(match comment.take() {
+                    Some(existing) => format!("{} {}", existing, text),
+                    None => text,
+                });
+            }
+            Token::VariationStart => {
+                // a variation replaces the move just played
+                let parent = self.game.nodes[self.current].parent.ok_or_else(|| {
+                    format!("Game {}: variation before the first move", self.number)
+                })?;
+                self.variations.push(self.current);
+                self.current = parent;
+            }
+            Token::VariationEnd => {
+                self.current = self
+                    .variations
+                    .pop()
+                    .ok_or_else(|| format!("Game {}: unmatched ')'", self.number))?;
+            }
+            Token::Move(text) => {
+                let board = self.game.board_at(self.current);
+                let mv = parse_move(&board, &text).map_err(|e| {
+                    format!("Game {}, ply {}: {}", self.number, self.game.ply(self.current) + 1, e)
+                })?;
+                self.current = self.game.add_move(self.current, mv);
+            }
+            Token::Tag(..) | Token::Result(_) => {}
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Game, String> {
+        if !self.variations.is_empty() {
+            return Err(format!("Game {}: unterminated variation", self.number));
+        }
+        Ok(self.game)
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '[' => {
+                chars.next();
+                let body: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                tokens.push(parse_tag(&body)?);
+            }
+            '{' => {
+                chars.next();
+                let comment: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            ';' => {
+                chars.next();
+                let comment: String = chars.by_ref().take_while(|&c| c != '\n').collect();
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::VariationStart);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::VariationEnd);
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "[]{}();".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                if let Some(token) = parse_word(&word) {
+                    tokens.push(token);
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// [Name "value"], with \" and \\ escapes inside the value
+fn parse_tag(body: &str) -> Result<Token, String> {
+    let body = body.trim();
+    let (name, rest) = body
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| format!("Malformed tag: [{}]", body))?;
+    let rest = rest.trim();
+    if !rest.starts_with('"') || !rest.ends_with('"') || rest.len() < 2 {
+        return Err(format!("Malformed tag: [{}]", body));
+    }
+    let mut value = String::new();
+    let mut inner = rest[1..rest.len() - 1].chars();
+    while let Some(c) = inner.next() {
+        if c == '\\' {
+            value.extend(inner.next());
+        } else {
+            value.push(c);
+        }
+    }
+    Ok(Token::Tag(name.to_string(), value))
+}
+
+// A movetext word: a result, a NAG, a move number, or a move that may be
+// glued to its number ("1.h2e2") or carry a suffix annotation ("h2e2!?")
+fn parse_word(word: &str) -> Option<Token> {
+    match word {
+        "1-0" | "0-1" | "1/2-1/2" | "*" => return Some(Token::Result(word.to_string())),
+        _ => {}
+    }
+    if word.starts_with('$') {
+        return None;
+    }
+    let digits = word.chars().take_while(|c| c.is_ascii_digit()).count();
+    let rest = if digits > 0 && word[digits..].starts_with('.') {
+        word[digits..].trim_start_matches('.')
+    } else {
+        word
+    };
+    let rest = rest.trim_end_matches(['!', '?']);
+    if rest.is_empty() {
+        None
+    } else {
+        Some(Token::Move(rest.to_string()))
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Writes the moves following `node`: the main continuation, then each
+// alternative in parentheses, then the rest of the main line
+fn write_line(game: &Game, node: usize, board: &Board, mut show_number: bool, format: &str, tokens: &mut Vec<String>) {
+    let mut node = node;
+    let mut board = board.clone();
+    while let Some(&main) = game.nodes[node].children.first() {
+        let alternatives = &game.nodes[node].children[1..];
+        write_move(game, main, &board, show_number, format, tokens);
+        for &alternative in alternatives {
+            let first = tokens.len();
+            write_move(game, alternative, &board, true, format, tokens);
+            let mut after = board.clone();
+            if let Some(mv) = &game.nodes[alternative].mv {
+                after.make_move(mv.from, mv.to);
+            }
+            write_line(game, alternative, &after, false, format, tokens);
+            tokens[first].insert(0, '(');
+            if let Some(last) = tokens.last_mut() {
+                last.push(')');
+            }
+        }
+        if let Some(mv) = &game.nodes[main].mv {
+            board.make_move(mv.from, mv.to);
+        }
+        show_number = !alternatives.is_empty() || game.nodes[main].comment.is_some();
+        node = main;
+    }
+}
+
+fn write_move(game: &Game, node: usize, board: &Board, show_number: bool, format: &str, tokens: &mut Vec<String>) {
+    let mv: &Move = match &game.nodes[node].mv {
+        Some(mv) => mv,
+        None => return,
+    };
+    let text = match format {
+        "wxf" => format_wxf(board, mv),
+        "chinese" => format_chinese(board, mv),
+        _ => Ok(mv.to_uci()),
+    }
+    .unwrap_or_else(|_| mv.to_uci());
+    // the number stays on the same line as its move
+    let number = board.fullmove_number;
+    if board.red_to_move {
+        tokens.push(format!("{}. {}", number, text));
+    } else if show_number {
+        tokens.push(format!("{}... {}", number, text));
+    } else {
+        tokens.push(text);
+    }
+    if let Some(comment) = &game.nodes[node].comment {
+        tokens.push(format!("{{{}}}", comment.replace('}', ")")));
+    }
+}
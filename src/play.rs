@@ -0,0 +1,279 @@
+use std::io::{self, BufRead, Write};
+use std::fs;
+use chess_engine::board::piece_letter;
+use chess_engine::game::ROOT;
+use chess_engine::notation::{format_chinese, format_wxf, parse_move};
+use chess_engine::pgn::write_pgn;
+use chess_engine::search::DEFAULT_HASH_MB;
+use chess_engine::{generate_legal_moves, has_legal_move, Board, Color, Move, SearchLimits, Searcher};
+
+pub const DEFAULT_PLAY_MOVETIME_MS: u64 = 3000;
+
+// play [red|black] [movetime ms]
+pub fn parse_play_args(args: &[String]) -> Result<(Color, u64), String> {
+    let mut human = Color::Red;
+    let mut movetime_ms = DEFAULT_PLAY_MOVETIME_MS;
+    for arg in args {
+        match arg.to_lowercase().as_str() {
+            "red" | "r" | "w" => human = Color::Red,
+            "black" | "b" => human = Color::Black,
+            value => {
+                if let Ok(ms) = value.parse() {
+                    movetime_ms = ms;
+                }
+            }
+        }
+    }
+    if movetime_ms == 0 {
+        return Err("the engine needs a movetime of at least 1 ms".to_string());
+    }
+    Ok((human, movetime_ms))
+}
+
+// Human against the engine in the terminal. Moves are accepted in ICCS,
+// WXF or Chinese notation; the engine thinks for a fixed time per move.
+pub fn run_play(human: Color, movetime_ms: u64) {
+    let mut game = Game {
+        board: Board::new(),
+        history: Vec::new(),
+        moves: Vec::new(),
+        searcher: Searcher::new(DEFAULT_HASH_MB, 1),
+        human,
+        movetime_ms,
+        flipped: human == Color::Black,
+    };
+    game.board.setup_initial_position();
+
+    println!("You play {}. Type 'help' for commands.", color_name(human));
+    game.print_board();
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut announced = false;
+    loop {
+        if game.is_over() {
+            if !announced {
+                println!("{} wins.", color_name(game.side_to_move().opponent()));
+                announced = true;
+            }
+        } else if game.side_to_move() != game.human {
+            game.engine_move();
+            continue;
+        } else {
+            // undo can take the game back out of a finished position
+            announced = false;
+        }
+
+        print!("{}> ", color_name(game.side_to_move()));
+        io::stdout().flush().unwrap();
+        let input = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        let tokens: Vec<&str> = input.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+
+        match tokens[0] {
+            "quit" | "exit" => break,
+            "help" => print_help(),
+            "undo" => game.undo(),
+            "hint" => game.hint(),
+            "flip" => {
+                game.flipped = !game.flipped;
+                game.print_board();
+            }
+            "fen" => println!("{}", game.board.to_fen()),
+            "save" => match tokens.get(1) {
+                Some(path) => match game.save(path) {
+                    Ok(()) => println!("Game saved to {}", path),
+                    Err(e) => println!("Error: {}", e),
+                },
+                None => println!("Usage: save <file.pgn>"),
+            },
+            "time" => match tokens.get(1).and_then(|t| t.parse().ok()).filter(|&ms| ms > 0) {
+                Some(ms) => {
+                    game.movetime_ms = ms;
+                    println!("Engine now thinks {} ms per move", ms);
+                }
+                None => println!("Usage: time <milliseconds>, at least 1"),
+            },
+            _ if game.is_over() => println!("The game is over. Use 'undo' or 'quit'."),
+            _ => match parse_move(&game.board, &input) {
+                Ok(mv) => {
+                    game.play(&mv);
+                    game.print_board();
+                }
+                Err(e) => println!("{}", e),
+            },
+        }
+    }
+}
+
+struct Game {
+    board: Board,
+    history: Vec<Board>,
+    moves: Vec<Move>,
+    searcher: Searcher,
+    human: Color,
+    movetime_ms: u64,
+    flipped: bool,
+}
+
+impl Game {
+    fn side_to_move(&self) -> Color {
+        if self.board.red_to_move {
+            Color::Red
+        } else {
+            Color::Black
+        }
+    }
+
+    fn is_over(&self) -> bool {
+        !has_legal_move(&self.board)
+    }
+
+    fn play(&mut self, mv: &Move) {
+        self.history.push(self.board.clone());
+        self.moves.push(mv.clone());
+        self.board.make_move(mv.from, mv.to);
+    }
+
+    // The search's move, or any legal move should it come back empty
+    fn search(&self) -> Option<Move> {
+        let limits = SearchLimits {
+            time_limit: Some(self.movetime_ms),
+            ..Default::default()
+        };
+        self.searcher.search(&self.board, &limits, &mut ()).best_move.or_else(|| {
+            generate_legal_moves(&self.board)
+                .into_iter()
+                .find(|mv| self.board.clone().make_move(mv.from, mv.to))
+        })
+    }
+
+    // Only called while the engine has a legal move, so it always plays one
+    fn engine_move(&mut self) {
+        println!("Engine is thinking...");
+        let mv = self.search().expect("the engine has a legal move");
+        let notation = self.describe(&mv);
+        self.play(&mv);
+        println!("Engine plays {}", notation);
+        self.print_board();
+    }
+
+    fn hint(&self) {
+        match self.search() {
+            Some(mv) => println!("Hint: {}", self.describe(&mv)),
+            None => println!("No legal moves"),
+        }
+    }
+
+    // The move in ICCS, WXF and Chinese notation, e.g. "h2e2 (C2=5, 炮二平五)"
+    fn describe(&self, mv: &Move) -> String {
+        match (format_wxf(&self.board, mv), format_chinese(&self.board, mv)) {
+            (Ok(wxf), Ok(chinese)) => format!("{} ({}, {})", mv, wxf, chinese),
+            _ => mv.to_string(),
+        }
+    }
+
+    // Writes the game so far as PGN
+    fn save(&self, path: &str) -> Result<(), String> {
+        let start = self.history.first().unwrap_or(&self.board).clone();
+        let mut record = chess_engine::Game::new(start);
+        let engine = "XiangqiEngine";
+        let (red, black) = match self.human {
+            Color::Red => ("Human", engine),
+            Color::Black => (engine, "Human"),
+        };
+        let result = match (self.is_over(), self.side_to_move()) {
+            (false, _) => "*",
+            (true, Color::Red) => "0-1",
+            (true, Color::Black) => "1-0",
+        };
+        for (name, value) in [
+            ("Game", "Chinese Chess"),
+            ("Variant", "xiangqi"),
+            ("Red", red),
+            ("Black", black),
+            ("Result", result),
+            ("Format", "ICCS"),
+        ] {
+            record.set_header(name, value);
+        }
+        let mut node = ROOT;
+        for mv in &self.moves {
+            node = record.add_move(node, mv.clone());
+        }
+        fs::write(path, write_pgn(&record)).map_err(|e| format!("cannot write {}: {}", path, e))
+    }
+
+    // Takes back moves until it is the human's turn again
+    fn undo(&mut self) {
+        let mut undone = false;
+        while let Some(board) = self.history.pop() {
+            self.board = board;
+            self.moves.pop();
+            undone = true;
+            if self.side_to_move() == self.human {
+                break;
+            }
+        }
+        if undone {
+            self.print_board();
+        } else {
+            println!("Nothing to undo");
+        }
+    }
+
+    // Uppercase letters are Red, lowercase Black, with Black at the top
+    // unless the board is flipped
+    fn print_board(&self) {
+        let ranks: Vec<usize> = if self.flipped { (0..10).rev().collect() } else { (0..10).collect() };
+        let files: Vec<usize> = if self.flipped { (0..9).rev().collect() } else { (0..9).collect() };
+        let file_labels: Vec<String> = files.iter().map(|&f| ((b'a' + f as u8) as char).to_string()).collect();
+
+        println!();
+        println!("    {}", file_labels.join(" "));
+        for (row, &rank) in ranks.iter().enumerate() {
+            if row == 5 {
+                println!("    ~~~~~ river ~~~~~");
+            }
+            let squares: Vec<String> = files
+                .iter()
+                .map(|&file| match self.board.squares[rank][file].piece {
+                    Some((color, piece)) => piece_letter(color, piece).to_string(),
+                    None => ".".to_string(),
+                })
+                .collect();
+            println!(" {}  {}", 9 - rank, squares.join(" "));
+        }
+        println!();
+        let color = self.side_to_move();
+        if self.board.is_in_check(color) {
+            println!("{} to move, in check", color_name(color));
+        } else {
+            println!("{} to move", color_name(color));
+        }
+    }
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::Red => "Red",
+        Color::Black => "Black",
+    }
+}
+
+fn print_help() {
+    println!("Enter moves as ICCS (h2e2), WXF (C2=5, H8+7, C+=5) or Chinese (炮二平五, 马8进7).");
+    println!("Commands:");
+    println!("  undo      - Take back your last move and the engine's reply");
+    println!("  hint      - Ask the engine for a move suggestion");
+    println!("  flip      - Turn the board around");
+    println!("  fen       - Print the current position as FEN");
+    println!("  save <f>  - Save the game as PGN");
+    println!("  time <ms> - Set the engine's thinking time per move");
+    println!("  quit      - Leave the game");
+}
@@ -1,67 +1,102 @@
 use crate::board::{Board, Color, Piece};
-use crate::evaluation::evaluate_position;
+use crate::eval_params::EvalParams;
+use crate::evaluation::evaluate_with;
 use crate::moves::{generate_legal_moves, Move};
-use std::collections::HashMap;
+use crate::tt::{NodeType, TTEntry, TranspositionTable};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread;
 use std::time::Instant;
 
+pub const DEFAULT_HASH_MB: usize = 16;
 const INFINITY: i32 = 50000;
+const DEFAULT_TIME_LIMIT: u64 = 1000; // ms per move when `go` gives no limit
+const DETERMINISTIC_NODES: u64 = 200_000; // node budget for a deterministic search without limits
 const MATE_SCORE: i32 = 49000;
 const MAX_DEPTH: i32 = 128; // Increased from 64 to allow deeper searches
 const LMR_LIMIT: i32 = 3; // Minimum depth for LMR
+const LMR_FULL_DEPTH_MOVES: i32 = 3; // Moves searched at full depth before reducing
+const LMR_MAX_MOVES: usize = 64; // Move-number dimension of the reduction table
+const LMR_BASE: f64 = 0.75;
+const LMR_DIVISOR: f64 = 2.25;
+const LMR_GOOD_HISTORY: i32 = 4000; // History score above which we reduce one ply less
 const IID_DEPTH: i32 = 5; // Minimum depth for Internal Iterative Deepening
 const HISTORY_PRUNING_THRESHOLD: i32 = -4000; // History score threshold for pruning
 const LATE_MOVE_PRUNING_LIMIT: i32 = 8;  // Number of moves to search fully before pruning
 const DELTA_PRUNING_MARGIN: i32 = 200;  // Margin for delta pruning in quiescence search
+const QS_CHECK_PLIES: usize = 1; // Quiescence plies that also try quiet checking moves
+const RESOLVE_PLIES: usize = 12; // Capture plies quiet_position follows at most
 const FUTILITY_MARGIN: [i32; 4] = [0, 100, 200, 300]; // Margins for depths 0-3
 const RAZOR_MARGIN: [i32; 4] = [0, 300, 500, 900]; // Razoring margins for depths 1-3
-const SEE_PIECE_VALUES: [i32; 7] = [0, 100, 450, 450, 650, 900, 10000]; // Pawn to King values for SEE
-
-// Piece values for MVV-LVA
+// Lazy SMP depth staggering: helper i skips blocks of SKIP_SIZE depths
+// offset by SKIP_PHASE, so threads spread over neighbouring depths
+const SKIP_SIZE: [i32; 20] = [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+const SKIP_PHASE: [i32; 20] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
+// Xiangqi values for SEE, indexed like get_piece_value:
+// General, Chariot, Cannon, Horse, Advisor, Elephant, Soldier
+const SEE_PIECE_VALUES: [i32; 7] = [6000, 600, 285, 270, 120, 120, 30];
+const SEE_CROSSED_SOLDIER_VALUE: i32 = 60;
+
+// Piece values for MVV-LVA, [victim][attacker] in get_piece_value order
 const MVV_LVA_SCORES: [[i32; 7]; 7] = [
-    [105, 205, 305, 405, 505, 605, 705],  // Victim Pawn
-    [104, 204, 304, 404, 504, 604, 704],  // Victim Knight
-    [103, 203, 303, 403, 503, 603, 703],  // Victim Bishop
-    [102, 202, 302, 402, 502, 602, 702],  // Victim Rook
-    [101, 201, 301, 401, 501, 601, 701],  // Victim Queen
-    [100, 200, 300, 400, 500, 600, 700],  // Victim King
-    [100, 200, 300, 400, 500, 600, 700],  // Victim None (for non-captures)
+    [701, 702, 703, 704, 705, 705, 706],  // Victim General
+    [601, 602, 603, 604, 605, 605, 606],  // Victim Chariot
+    [451, 452, 453, 454, 455, 455, 456],  // Victim Cannon
+    [401, 402, 403, 404, 405, 405, 406],  // Victim Horse
+    [201, 202, 203, 204, 205, 205, 206],  // Victim Advisor
+    [201, 202, 203, 204, 205, 205, 206],  // Victim Elephant
+    [101, 102, 103, 104, 105, 105, 106],  // Victim Soldier
 ];
 
-#[derive(Clone, Copy, PartialEq)]
-enum NodeType {
-    Exact,
-    LowerBound,
-    UpperBound,
+#[derive(Clone)]
+struct KillerMoves {
+    moves: [Option<Move>; 2],
 }
 
-#[derive(Clone)]
-struct TTEntry {
-    depth: i32,
-    score: i32,
-    node_type: NodeType,
-    best_move: Option<Move>,
+// Limits for one search. Without any limit the search gets DEFAULT_TIME_LIMIT.
+#[derive(Clone, Debug, Default)]
+pub struct SearchLimits {
+    pub time_limit: Option<u64>, // milliseconds
+    pub nodes: Option<u64>,
+    pub depth: Option<i32>,
 }
 
-#[derive(Clone)]
-struct KillerMoves {
-    moves: [Option<Move>; 2],
+impl SearchLimits {
+    pub fn is_empty(&self) -> bool {
+        self.time_limit.is_none() && self.nodes.is_none() && self.depth.is_none()
+    }
 }
 
-pub struct SearchInfo {
-    pub nodes: u64,
-    pub start_time: Instant,
-    pub time_limit: u64,
+struct SearchInfo {
+    nodes: u64,
+    start_time: Instant,
+    time_limit: Option<u64>,
+    node_limit: Option<u64>,
+    thread_id: usize,
     history_table: [[i32; 90]; 90],
     killer_moves: Vec<KillerMoves>,
-    tt: HashMap<u64, TTEntry>,
+    tt: Arc<TranspositionTable>,
+    stop: Arc<AtomicBool>,
+    params: Arc<EvalParams>,
+    // The main thread ignores all limits until depth 1 is done, so there is
+    // always a move to play
+    finishing_depth_one: bool,
 }
 
 impl SearchInfo {
-    pub fn new(time_limit: u64) -> Self {
+    fn new(
+        limits: &SearchLimits,
+        tt: Arc<TranspositionTable>,
+        stop: Arc<AtomicBool>,
+        params: Arc<EvalParams>,
+        thread_id: usize,
+    ) -> Self {
         SearchInfo {
             nodes: 0,
             start_time: Instant::now(),
-            time_limit,
+            time_limit: limits.time_limit,
+            node_limit: limits.nodes,
+            thread_id,
             history_table: [[0; 90]; 90],
             killer_moves: vec![
                 KillerMoves {
@@ -69,12 +104,37 @@ impl SearchInfo {
                 };
                 MAX_DEPTH as usize
             ],
-            tt: HashMap::new(),
+            tt,
+            stop,
+            params,
+            finishing_depth_one: thread_id == 0,
         }
     }
 
-    pub fn should_stop(&self) -> bool {
-        self.start_time.elapsed().as_millis() as u64 >= self.time_limit
+    fn should_stop(&self) -> bool {
+        if self.finishing_depth_one {
+            return false;
+        }
+        if self.stop.load(Ordering::Relaxed) {
+            return true;
+        }
+        if let Some(node_limit) = self.node_limit {
+            if self.nodes >= node_limit {
+                return true;
+            }
+        }
+        match self.time_limit {
+            Some(time_limit) => self.elapsed_ms() >= time_limit,
+            None => false,
+        }
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.start_time.elapsed().as_millis() as u64
+    }
+
+    fn is_main_thread(&self) -> bool {
+        self.thread_id == 0
     }
 
     fn update_killer_move(&mut self, mv: &Move, ply: usize) {
@@ -84,6 +144,10 @@ impl SearchInfo {
         }
     }
 
+    fn is_killer_move(&self, mv: &Move, ply: usize) -> bool {
+        self.killer_moves[ply].moves.iter().any(|k| k.as_ref() == Some(mv))
+    }
+
     fn update_history_score(&mut self, mv: &Move, depth: i32) {
         let from_idx = mv.from.0.min(9) * 9 + mv.from.1.min(8);
         let to_idx = mv.to.0.min(9) * 9 + mv.to.1.min(8);
@@ -97,19 +161,244 @@ impl SearchInfo {
     }
 }
 
+// What one search thread settled on when it stopped
+struct ThreadResult {
+    best_move: Option<Move>,
+    score: i32,
+    bound: ScoreBound,
+    depth: i32,
+    nodes: u64,
+    pv: Vec<Move>,
+}
+
+// How the reported score relates to the true value of the position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScoreBound {
+    Exact,
+    Lower, // the position is at least this good (fail high)
+    Upper, // the position is at most this good (fail low)
+}
+
+// Progress of the main thread: sent after each completed iteration, and for
+// aspiration-window failures with the matching bound
+pub struct IterationReport {
+    pub depth: i32,
+    pub score: i32,
+    pub bound: ScoreBound,
+    pub nodes: u64,
+    pub time_ms: u64,
+    pub pv: Vec<Move>,
+}
+
+// Final answer of a search, totalled over all threads
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub ponder_move: Option<Move>,
+    pub score: i32,
+    pub bound: ScoreBound,
+    pub depth: i32,
+    pub nodes: u64,
+    pub time_ms: u64,
+    pub pv: Vec<Move>,
+}
+
+// Receives progress from a running search. Both methods are called on the
+// thread that started the search.
+pub trait SearchObserver {
+    fn on_iteration(&mut self, _report: &IterationReport) {}
+    fn on_result(&mut self, _result: &SearchResult) {}
+}
+
+// The unit observer ignores everything
+impl SearchObserver for () {}
+
+// Owns the state that outlives a single search: the shared transposition
+// table, the number of threads to search with and the evaluation weights
+pub struct Searcher {
+    tt: Arc<TranspositionTable>,
+    threads: usize,
+    params: Arc<EvalParams>,
+    deterministic: bool,
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Searcher::new(DEFAULT_HASH_MB, 1)
+    }
+}
+
+impl Searcher {
+    pub fn new(hash_mb: usize, threads: usize) -> Self {
+        Searcher {
+            tt: Arc::new(TranspositionTable::new(hash_mb)),
+            threads: threads.max(1),
+            params: Arc::new(EvalParams::default()),
+            deterministic: false,
+        }
+    }
+
+    pub fn set_hash_size(&mut self, hash_mb: usize) {
+        self.tt = Arc::new(TranspositionTable::new(hash_mb));
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    // Reproducible searches: no wall clock, one thread and a fresh table, so
+    // the best move and node count match across runs and machines
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
+
+    pub fn eval_params(&self) -> &EvalParams {
+        &self.params
+    }
+
+    // Scores stored under the old weights no longer apply, so the table is
+    // cleared as well
+    pub fn set_eval_params(&mut self, params: EvalParams) {
+        self.params = Arc::new(params);
+        self.tt.clear();
+    }
+
+    // Forget everything learned so far, e.g. for a new game
+    pub fn clear(&self) {
+        self.tt.clear();
+    }
+
+    pub fn search(
+        &self,
+        board: &Board,
+        limits: &SearchLimits,
+        observer: &mut dyn SearchObserver,
+    ) -> SearchResult {
+        let mut limits = limits.clone();
+        let mut threads = self.threads;
+        if self.deterministic {
+            self.tt.clear();
+            threads = 1;
+            limits.time_limit = None;
+            if limits.is_empty() {
+                limits.nodes = Some(DETERMINISTIC_NODES);
+            }
+        }
+        if limits.is_empty() {
+            limits.time_limit = Some(DEFAULT_TIME_LIMIT);
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let start_time = Instant::now();
+
+        // Lazy SMP: helpers run the same iterative deepening on their own
+        // history and killers, sharing only the transposition table
+        let helpers: Vec<_> = (1..threads)
+            .map(|thread_id| {
+                let board = board.clone();
+                let mut info = SearchInfo::new(
+                    &limits,
+                    Arc::clone(&self.tt),
+                    Arc::clone(&stop),
+                    Arc::clone(&self.params),
+                    thread_id,
+                );
+                let max_depth = limits.depth;
+                thread::spawn(move || iterative_deepening(&board, &mut info, max_depth, &mut ()))
+            })
+            .collect();
+
+        let mut info = SearchInfo::new(
+            &limits,
+            Arc::clone(&self.tt),
+            Arc::clone(&stop),
+            Arc::clone(&self.params),
+            0,
+        );
+        let main_result = iterative_deepening(board, &mut info, limits.depth, observer);
+        stop.store(true, Ordering::Relaxed);
+
+        let mut nodes = main_result.nodes;
+        let mut best = main_result;
+        for helper in helpers {
+            if let Ok(result) = helper.join() {
+                nodes += result.nodes;
+                // Prefer the deepest completed iteration, then the best score
+                let better = result.best_move.is_some()
+                    && (best.best_move.is_none()
+                        || result.depth > best.depth
+                        || (result.depth == best.depth && result.score > best.score));
+                if better {
+                    best = result;
+                }
+            }
+        }
+
+        let ponder_move = match best.pv.as_slice() {
+            [first, second, ..] if Some(first) == best.best_move.as_ref() => Some(second.clone()),
+            _ => None,
+        };
+        let result = SearchResult {
+            best_move: best.best_move,
+            ponder_move,
+            score: best.score,
+            bound: best.bound,
+            depth: best.depth,
+            nodes,
+            time_ms: start_time.elapsed().as_millis() as u64,
+            pv: best.pv,
+        };
+        observer.on_result(&result);
+        result
+    }
+}
+
+// One-shot search with a fresh table and default limits
 pub fn find_best_move(board: &Board) -> Option<Move> {
-    let mut info = SearchInfo::new(1000);
-    iterative_deepening(board, &mut info)
+    Searcher::default().search(board, &SearchLimits::default(), &mut ()).best_move
 }
 
-fn iterative_deepening(board: &Board, info: &mut SearchInfo) -> Option<Move> {
-    let mut best_move = None;
+// Moves to mate for a mate score, negative when the side to move is mated
+pub fn mate_in(score: i32) -> Option<i32> {
+    if score.abs() > MATE_SCORE - MAX_DEPTH {
+        let moves = (MATE_SCORE - score.abs() + 1) / 2;
+        Some(if score > 0 { moves } else { -moves })
+    } else {
+        None
+    }
+}
+
+fn iterative_deepening(
+    board: &Board,
+    info: &mut SearchInfo,
+    max_depth: Option<i32>,
+    observer: &mut dyn SearchObserver,
+) -> ThreadResult {
+    let mut result = ThreadResult {
+        best_move: None,
+        score: -INFINITY,
+        bound: ScoreBound::Exact,
+        depth: 0,
+        nodes: 0,
+        pv: Vec::new(),
+    };
     let mut prev_depth_time = 0;
     let mut prev_score = 0;
     let mut window_size = 50;
 
-    for depth in 1..=MAX_DEPTH {
-        let depth_start = info.start_time.elapsed().as_millis() as u64;
+    for depth in 1..=max_depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH) {
+        if !info.is_main_thread() {
+            let i = (info.thread_id - 1) % SKIP_SIZE.len();
+            if ((depth + SKIP_PHASE[i]) / SKIP_SIZE[i]) % 2 != 0 {
+                continue;
+            }
+        }
+
+        let depth_start = info.elapsed_ms();
+        // Last complete search of this depth that fell outside its window
+        let mut failed_search = None;
 
         let (score, mv) = if depth > 4 {
             let mut alpha = prev_score - window_size;
@@ -117,6 +406,26 @@ fn iterative_deepening(board: &Board, info: &mut SearchInfo) -> Option<Move> {
             let mut current_result = negamax_root(board, depth, alpha, beta, info);
 
             loop {
+                let failed = current_result.0 <= alpha || current_result.0 >= beta;
+                if failed && !info.should_stop() {
+                    let bound = if current_result.0 <= alpha {
+                        ScoreBound::Upper
+                    } else {
+                        ScoreBound::Lower
+                    };
+                    if info.is_main_thread() {
+                        observer.on_iteration(&IterationReport {
+                            depth,
+                            score: current_result.0,
+                            bound,
+                            nodes: info.nodes,
+                            time_ms: info.elapsed_ms(),
+                            pv: current_result.1.iter().cloned().collect(),
+                        });
+                    }
+                    failed_search = Some((current_result.clone(), bound));
+                }
+
                 if current_result.0 <= alpha {
                     window_size *= 2;
                     alpha = current_result.0 - window_size;
@@ -139,80 +448,176 @@ fn iterative_deepening(board: &Board, info: &mut SearchInfo) -> Option<Move> {
             negamax_root(board, depth, -INFINITY, INFINITY, info)
         };
 
-        if !info.should_stop() {
-            best_move = mv;
-            prev_score = score;
-            let depth_time = info.start_time.elapsed().as_millis() as u64 - depth_start;
-            let total_time = info.start_time.elapsed().as_millis() as u64;
-
-            if score.abs() > MATE_SCORE - 1000 {
-                break;
+        if info.should_stop() {
+            // Stopped while re-searching a failed aspiration window: the
+            // failed search still bounds the score, and after a fail high its
+            // move is better than the one from the previous depth
+            if let Some(((score, mv), bound)) = failed_search {
+                result.score = score;
+                result.bound = bound;
+                if bound == ScoreBound::Lower && mv.is_some() {
+                    result.pv = extract_pv(board, &info.tt, mv.as_ref(), depth);
+                    result.best_move = mv;
+                }
             }
+            break;
+        }
+
+        info.finishing_depth_one = false;
+        result.pv = extract_pv(board, &info.tt, mv.as_ref(), depth);
+        result.best_move = mv;
+        result.score = score;
+        result.bound = ScoreBound::Exact;
+        result.depth = depth;
+        prev_score = score;
+        let total_time = info.elapsed_ms();
+        let depth_time = total_time - depth_start;
+
+        if info.is_main_thread() {
+            observer.on_iteration(&IterationReport {
+                depth,
+                score,
+                bound: ScoreBound::Exact,
+                nodes: info.nodes,
+                time_ms: total_time,
+                pv: result.pv.clone(),
+            });
+        }
+
+        if score.abs() > MATE_SCORE - 1000 {
+            break;
+        }
 
+        // Only the main thread manages time; helpers run until told to stop
+        if let (Some(time_limit), true) = (info.time_limit, info.is_main_thread()) {
             if depth > 4 {
-                if depth_time > prev_depth_time * 2 && total_time > info.time_limit / 2 {
+                if depth_time > prev_depth_time * 2 && total_time > time_limit / 2 {
                     break;
                 }
-                if total_time > info.time_limit * 3 / 4 {
+                if total_time > time_limit * 3 / 4 {
                     break;
                 }
             }
-            prev_depth_time = depth_time;
         }
+        prev_depth_time = depth_time;
+    }
+
+    result.nodes = info.nodes;
+    result
+}
+
+// Start from the root move this thread chose (another thread may have
+// replaced the root entry) and follow best moves through the transposition
+// table, stopping at the first missing or illegal entry or a repeated position
+fn extract_pv(board: &Board, tt: &TranspositionTable, root_move: Option<&Move>, depth: i32) -> Vec<Move> {
+    let mut pv = Vec::new();
+    let mut seen = Vec::new();
+    let mut current = board.clone();
+
+    while pv.len() < depth.max(1) as usize {
+        let hash = compute_hash(&current);
+        if seen.contains(&hash) {
+            break;
+        }
+        seen.push(hash);
+
+        let next = match (pv.is_empty(), root_move) {
+            (true, Some(mv)) => Some(mv.clone()),
+            _ => tt.probe(hash).and_then(|entry| entry.best_move),
+        };
+        let Some(mv) = next else {
+            break;
+        };
+        if !generate_legal_moves(&current).contains(&mv) || !current.make_move(mv.from, mv.to) {
+            break;
+        }
+        pv.push(mv);
     }
 
-    best_move
+    pv
 }
 
 fn negamax_root(
     board: &Board,
     depth: i32,
-    alpha: i32,
+    mut alpha: i32,
     beta: i32,
     info: &mut SearchInfo,
 ) -> (i32, Option<Move>) {
     let mut best_move = None;
     let mut best_score = -INFINITY;
+    let original_alpha = alpha;
     let hash = compute_hash(board);
 
-    if let Some(tt_entry) = info.tt.get(&hash) {
-        if tt_entry.depth >= depth {
-            if tt_entry.node_type == NodeType::Exact {
-                return (tt_entry.score, tt_entry.best_move.clone());
-            }
+    let mut tt_move = None;
+    if let Some(tt_entry) = info.tt.probe(hash) {
+        if tt_entry.depth >= depth && tt_entry.node_type == NodeType::Exact {
+            return (tt_entry.score, tt_entry.best_move.clone());
         }
+        tt_move = tt_entry.best_move.clone();
     }
 
     let mut moves = generate_legal_moves(board);
-    sort_moves(board, &mut moves, info, 0, None);
+    sort_moves(board, &mut moves, info, 0, tt_move.as_ref());
 
+    let mut moves_searched = 0;
     for mv in moves {
         let mut new_board = board.clone();
         if !new_board.make_move(mv.from, mv.to) {
             continue;
         }
+        moves_searched += 1;
 
-        let score = -negamax(&new_board, depth - 1, -beta, -alpha, info, 1);
+        // PVS: the first move gets the full window, the rest are scouted
+        // with a null window and only re-searched if they beat alpha
+        let score = if moves_searched == 1 {
+            -negamax(&new_board, depth - 1, -beta, -alpha, info, 1)
+        } else {
+            let mut score = -negamax(&new_board, depth - 1, -alpha - 1, -alpha, info, 1);
+            if score > alpha && score < beta {
+                score = -negamax(&new_board, depth - 1, -beta, -alpha, info, 1);
+            }
+            score
+        };
+
+        if info.should_stop() {
+            break;
+        }
 
         if score > best_score {
             best_score = score;
             best_move = Some(mv.clone());
+
+            if score > alpha {
+                alpha = score;
+            }
         }
 
-        if info.should_stop() {
+        if alpha >= beta {
             break;
         }
     }
 
-    info.tt.insert(
-        hash,
-        TTEntry {
-            depth,
-            score: best_score,
-            node_type: NodeType::Exact,
-            best_move: best_move.clone(),
-        },
-    );
+    let node_type = if best_score <= original_alpha {
+        NodeType::UpperBound
+    } else if best_score >= beta {
+        NodeType::LowerBound
+    } else {
+        NodeType::Exact
+    };
+
+    // An interrupted search leaves scores that are not worth sharing
+    if !info.should_stop() {
+        info.tt.store(
+            hash,
+            TTEntry {
+                depth,
+                score: best_score,
+                node_type,
+                best_move: best_move.clone(),
+            },
+        );
+    }
 
     (best_score, best_move)
 }
@@ -231,9 +636,14 @@ fn negamax(
         return 0;
     }
 
+    // Check extensions can keep a line alive indefinitely
+    if ply >= MAX_DEPTH as usize - 1 {
+        return evaluate_with(board, &info.params);
+    }
+
     let hash = compute_hash(board);
     let mut tt_move = None;
-    if let Some(tt_entry) = info.tt.get(&hash) {
+    if let Some(tt_entry) = info.tt.probe(hash) {
         tt_move = tt_entry.best_move.clone();
         if tt_entry.depth >= depth {
             match tt_entry.node_type {
@@ -258,33 +668,31 @@ fn negamax(
     }
 
     if depth <= 0 {
-        return quiescence_search(board, alpha, beta, info);
+        return quiescence_search(board, alpha, beta, info, ply, 0);
     }
 
     if !is_in_check && depth <= 3 {
-        let eval = evaluate_position(board);
+        let eval = evaluate_with(board, &info.params);
         let razor_margin = RAZOR_MARGIN[depth as usize];
 
         if eval + razor_margin <= alpha {
-            let q_score = quiescence_search(board, alpha - razor_margin, alpha - razor_margin + 1, info);
+            let q_score = quiescence_search(board, alpha - razor_margin, alpha - razor_margin + 1, info, ply, 0);
             if q_score + razor_margin <= alpha {
                 return q_score;
             }
         }
     }
 
+    // Without a legal move the side to move has lost, in check or not
     let mut moves = generate_legal_moves(board);
     if moves.is_empty() {
-        if is_in_check {
-            return -MATE_SCORE + ply as i32;
-        }
-        return 0;
+        return -MATE_SCORE + ply as i32;
     }
 
     if depth >= IID_DEPTH && tt_move.is_none() {
         let iid_depth = depth - 2;
         negamax(board, iid_depth, alpha, beta, info, ply);
-        if let Some(tt_entry) = info.tt.get(&hash) {
+        if let Some(tt_entry) = info.tt.probe(hash) {
             tt_move = tt_entry.best_move.clone();
         }
     }
@@ -295,7 +703,14 @@ fn negamax(
     let mut node_type = NodeType::UpperBound;
     let mut best_move = None;
     let mut moves_searched = 0;
-    let static_eval = evaluate_position(board);
+    let static_eval = evaluate_with(board, &info.params);
+
+    let is_pv = beta - alpha > 1;
+    let opponent = if board.red_to_move {
+        Color::Black
+    } else {
+        Color::Red
+    };
 
     for mv in &moves {
         let mut new_board = board.clone();
@@ -303,38 +718,66 @@ fn negamax(
             continue;
         }
 
-        let mut score;
         moves_searched += 1;
+        let capture = is_capture(board, mv);
 
-        if is_capture(board, mv) && moves_searched > 1 {
+        if capture && moves_searched > 1 {
             let see_score = see(board, mv);
             if see_score < -50 {
                 continue;
             }
         }
 
-        if depth <= 3 && !is_in_check && moves_searched > 1 && !is_capture(board, mv) {
+        if depth <= 3 && !is_in_check && moves_searched > 1 && !capture {
             let margin = FUTILITY_MARGIN[depth as usize];
             if static_eval + margin <= alpha {
                 continue;
             }
         }
 
-        if depth >= LMR_LIMIT && moves_searched > 3 && !is_in_check && !is_capture(board, mv) {
-            let history_score = info.get_history_score(mv);
+        let mut score;
+        if moves_searched == 1 {
+            score = -negamax(&new_board, depth - 1, -beta, -alpha, info, ply + 1);
+        } else {
+            let mut reduction = 0;
+
+            if depth >= LMR_LIMIT && moves_searched > LMR_FULL_DEPTH_MOVES && !is_in_check && !capture {
+                let history_score = info.get_history_score(mv);
 
-            if history_score < HISTORY_PRUNING_THRESHOLD && depth <= 3 {
-                continue;
+                if history_score < HISTORY_PRUNING_THRESHOLD && depth <= 3 {
+                    continue;
+                }
+
+                reduction = lmr_reduction(depth, moves_searched);
+                if is_pv {
+                    reduction -= 1;
+                }
+                if info.is_killer_move(mv, ply) {
+                    reduction -= 1;
+                }
+                if new_board.is_in_check(opponent) {
+                    reduction -= 1;
+                }
+                if history_score > LMR_GOOD_HISTORY {
+                    reduction -= 1;
+                } else if history_score < 0 {
+                    reduction += 1;
+                }
+                reduction = reduction.clamp(0, depth - 2);
             }
 
-            let reduction = if history_score < 0 { 2 } else { 1 };
-            score = -negamax(&new_board, depth - 1 - reduction, -beta, -alpha, info, ply + 1);
+            // Null-window scout, possibly reduced
+            score = -negamax(&new_board, depth - 1 - reduction, -alpha - 1, -alpha, info, ply + 1);
 
-            if score > alpha {
+            // The reduced search beat alpha: verify at full depth
+            if score > alpha && reduction > 0 {
+                score = -negamax(&new_board, depth - 1, -alpha - 1, -alpha, info, ply + 1);
+            }
+
+            // Inside the window on a PV node: get an exact score
+            if score > alpha && score < beta {
                 score = -negamax(&new_board, depth - 1, -beta, -alpha, info, ply + 1);
             }
-        } else {
-            score = -negamax(&new_board, depth - 1, -beta, -alpha, info, ply + 1);
         }
 
         if score > best_score {
@@ -361,71 +804,215 @@ fn negamax(
             break;
         }
 
-        if moves_searched > LATE_MOVE_PRUNING_LIMIT {
-            if score <= alpha - DELTA_PRUNING_MARGIN {
-                break;
-            }
+        if moves_searched > LATE_MOVE_PRUNING_LIMIT && score <= alpha - DELTA_PRUNING_MARGIN {
+            break;
         }
     }
 
-    info.tt.insert(
-        hash,
-        TTEntry {
-            depth,
-            score: best_score,
-            node_type,
-            best_move,
-        },
-    );
+    // Every generated move left the general in check
+    if moves_searched == 0 {
+        return -MATE_SCORE + ply as i32;
+    }
+
+    if !info.should_stop() {
+        info.tt.store(
+            hash,
+            TTEntry {
+                depth,
+                score: best_score,
+                node_type,
+                best_move,
+            },
+        );
+    }
 
     best_score
 }
 
-fn quiescence_search(board: &Board, mut alpha: i32, beta: i32, info: &mut SearchInfo) -> i32 {
+fn lmr_reduction(depth: i32, moves_searched: i32) -> i32 {
+    static LMR_TABLE: OnceLock<Vec<[i32; LMR_MAX_MOVES]>> = OnceLock::new();
+
+    // reductions grow with log(depth) * log(move number)
+    let table = LMR_TABLE.get_or_init(|| {
+        let mut table = vec![[0; LMR_MAX_MOVES]; MAX_DEPTH as usize + 1];
+        for (d, row) in table.iter_mut().enumerate().skip(1) {
+            for (m, reduction) in row.iter_mut().enumerate().skip(1) {
+                *reduction = (LMR_BASE + (d as f64).ln() * (m as f64).ln() / LMR_DIVISOR) as i32;
+            }
+        }
+        table
+    });
+
+    let d = (depth.max(0) as usize).min(MAX_DEPTH as usize);
+    let m = (moves_searched.max(0) as usize).min(LMR_MAX_MOVES - 1);
+    table[d][m]
+}
+
+fn quiescence_search(
+    board: &Board,
+    mut alpha: i32,
+    beta: i32,
+    info: &mut SearchInfo,
+    ply: usize,
+    qs_ply: usize,
+) -> i32 {
     info.nodes += 1;
 
     if info.should_stop() {
         return 0;
     }
 
-    let stand_pat = evaluate_position(board);
-    
-    if stand_pat >= beta {
-        return beta;
+    if ply >= MAX_DEPTH as usize - 1 {
+        return evaluate_with(board, &info.params);
     }
 
-    // Delta pruning
-    if stand_pat < alpha - DELTA_PRUNING_MARGIN {
-        return alpha;
+    // Any stored result is at least as deep as a quiescence search
+    let hash = compute_hash(board);
+    let mut tt_move = None;
+    if let Some(tt_entry) = info.tt.probe(hash) {
+        tt_move = tt_entry.best_move.clone();
+        let usable = match tt_entry.node_type {
+            NodeType::Exact => true,
+            NodeType::LowerBound => tt_entry.score >= beta,
+            NodeType::UpperBound => tt_entry.score <= alpha,
+        };
+        if usable {
+            return tt_entry.score;
+        }
     }
 
-    if stand_pat > alpha {
-        alpha = stand_pat;
+    let (side, opponent) = if board.red_to_move {
+        (Color::Red, Color::Black)
+    } else {
+        (Color::Black, Color::Red)
+    };
+    let is_in_check = board.is_in_check(side);
+    let original_alpha = alpha;
+
+    // In check there is no standing pat: every evasion has to be tried
+    let mut best_score = -INFINITY;
+    if !is_in_check {
+        let stand_pat = evaluate_with(board, &info.params);
+
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+
+        // Delta pruning
+        if stand_pat < alpha - DELTA_PRUNING_MARGIN {
+            return stand_pat;
+        }
+
+        if stand_pat > alpha {
+            alpha = stand_pat;
+        }
+        best_score = stand_pat;
     }
 
     let mut moves = generate_legal_moves(board);
-    sort_moves(board, &mut moves, info, 0, None);
+    sort_moves(board, &mut moves, info, ply, tt_move.as_ref());
 
-    // Only search captures
-    moves.retain(|mv| is_capture(board, mv));
+    let try_quiet_checks = qs_ply < QS_CHECK_PLIES;
+    let mut best_move = None;
+    let mut legal_moves = 0;
 
     for mv in moves {
+        let capture = is_capture(board, &mv);
+        if !is_in_check && !capture && !try_quiet_checks {
+            continue;
+        }
+
         let mut new_board = board.clone();
         if !new_board.make_move(mv.from, mv.to) {
             continue;
         }
+        legal_moves += 1;
+
+        if !is_in_check && !capture && !new_board.is_in_check(opponent) {
+            continue;
+        }
 
-        let score = -quiescence_search(&new_board, -beta, -alpha, info);
+        let score = -quiescence_search(&new_board, -beta, -alpha, info, ply + 1, qs_ply + 1);
 
-        if score >= beta {
-            return beta;
+        if score > best_score {
+            best_score = score;
+            if score > alpha {
+                alpha = score;
+                best_move = Some(mv.clone());
+            }
         }
-        if score > alpha {
-            alpha = score;
+
+        if score >= beta {
+            break;
         }
     }
 
-    alpha
+    if is_in_check && legal_moves == 0 {
+        return -MATE_SCORE + ply as i32;
+    }
+
+    let node_type = if best_score >= beta {
+        NodeType::LowerBound
+    } else if best_score > original_alpha {
+        NodeType::Exact
+    } else {
+        NodeType::UpperBound
+    };
+
+    // Never overwrite the result of a real search
+    if !info.should_stop() && info.tt.probe(hash).is_none_or(|entry| entry.depth <= 0) {
+        info.tt.store(
+            hash,
+            TTEntry {
+                depth: 0,
+                score: best_score,
+                node_type,
+                best_move,
+            },
+        );
+    }
+
+    best_score
+}
+
+// The position at the end of the principal variation of a capture-only
+// quiescence search, so that pending exchanges are played out before a
+// position is evaluated. The tuner evaluates its positions there.
+pub fn quiet_position(board: &Board, params: &EvalParams) -> Board {
+    resolve_captures(board, -INFINITY, INFINITY, params, 0).1
+}
+
+fn resolve_captures(board: &Board, mut alpha: i32, beta: i32, params: &EvalParams, ply: usize) -> (i32, Board) {
+    let stand_pat = evaluate_with(board, params);
+    let mut best = (stand_pat, board.clone());
+    if stand_pat >= beta || ply >= RESOLVE_PLIES {
+        return best;
+    }
+    alpha = alpha.max(stand_pat);
+
+    let mut captures: Vec<(i32, Move)> = generate_legal_moves(board)
+        .into_iter()
+        .filter(|mv| is_capture(board, mv))
+        .map(|mv| (see(board, &mv), mv))
+        .filter(|(gain, _)| *gain >= 0)
+        .collect();
+    captures.sort_by_key(|(gain, _)| -gain);
+
+    for (_, mv) in captures {
+        let mut new_board = board.clone();
+        if !new_board.make_move(mv.from, mv.to) {
+            continue;
+        }
+        let (score, leaf) = resolve_captures(&new_board, -beta, -alpha, params, ply + 1);
+        if -score > best.0 {
+            best = (-score, leaf);
+            alpha = alpha.max(-score);
+        }
+        if -score >= beta {
+            break;
+        }
+    }
+    best
 }
 
 fn is_capture(board: &Board, mv: &Move) -> bool {
@@ -461,8 +1048,8 @@ fn sort_moves(
             // Captures scored by MVV/LVA and SEE
             if let Some((_, victim_piece)) = board.squares[mv.to.0][mv.to.1].piece {
                 if let Some((_, attacker_piece)) = board.squares[mv.from.0][mv.from.1].piece {
-                    score += MVV_LVA_SCORES[get_piece_value_for_see(&victim_piece)]
-                        [get_piece_value_for_see(&attacker_piece)];
+                    score += MVV_LVA_SCORES[get_piece_value(victim_piece)]
+                        [get_piece_value(attacker_piece)];
                     
                     // Add SEE score for captures
                     score += see(board, mv);
@@ -495,49 +1082,73 @@ fn sort_moves(
     *moves = move_scores.into_iter().map(|ms| ms.mv).collect();
 }
 
-fn see(board: &Board, mv: &Move) -> i32 {
+// Static exchange evaluation on the target square of `mv`. After every
+// capture the next least valuable attacker is looked up again on the updated
+// board, so cannon screens that appear or vanish, horse legs that open up and
+// soldiers that can only attack sideways across the river are all respected.
+pub fn see(board: &Board, mv: &Move) -> i32 {
+    let target = mv.to;
+    let Some((_, victim)) = board.squares[target.0][target.1].piece else {
+        return 0;
+    };
+    let Some((mut side, mut attacker)) = board.squares[mv.from.0][mv.from.1].piece else {
+        return 0;
+    };
+
+    let mut scratch = board.clone();
     let mut gain = [0; 32];
     let mut depth = 0;
+    let mut from = mv.from;
+    gain[0] = see_piece_value(victim, side.opponent(), target);
 
-    if let Some((_, target_piece)) = board.squares[mv.to.0][mv.to.1].piece {
-        gain[depth] = SEE_PIECE_VALUES[get_piece_value_for_see(&target_piece)];
-
-        if let Some((_, attacker_piece)) = board.squares[mv.from.0][mv.from.1].piece {
-            let attacker_value = get_piece_value_for_see(&attacker_piece);
-            let target_value = get_piece_value_for_see(&target_piece);
+    loop {
+        depth += 1;
+        // Speculative score if the piece now arriving on the target is taken
+        gain[depth] = see_piece_value(attacker, side, target) - gain[depth - 1];
+        if (-gain[depth - 1]).max(gain[depth]) < 0 || depth + 1 >= gain.len() {
+            break;
+        }
 
-            if SEE_PIECE_VALUES[attacker_value] <= SEE_PIECE_VALUES[target_value] {
-                return gain[depth];
-            }
+        scratch.squares[target.0][target.1].piece = scratch.squares[from.0][from.1].piece;
+        scratch.squares[from.0][from.1].piece = None;
+        side = side.opponent();
 
-            gain[depth] -= SEE_PIECE_VALUES[attacker_value];
-            depth += 1;
+        let next = scratch
+            .attackers(target, side)
+            .into_iter()
+            .filter_map(|pos| scratch.squares[pos.0][pos.1].piece.map(|(_, piece)| (pos, piece)))
+            .min_by_key(|&(pos, piece)| see_piece_value(piece, side, pos));
 
-            if gain[0] < -500 {
-                return gain[0];
+        match next {
+            Some((pos, piece)) => {
+                from = pos;
+                attacker = piece;
             }
-
-            gain[depth] = SEE_PIECE_VALUES[attacker_value];
-            let score = -gain[depth - 1];
-            return score;
+            None => break,
         }
     }
-    0
+
+    while depth > 1 {
+        depth -= 1;
+        gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+    }
+    gain[0]
 }
 
-fn get_piece_value_for_see(piece: &Piece) -> usize {
-    match piece {
-        Piece::Soldier => 1,  // Pawn
-        Piece::Horse => 2,    // Knight
-        Piece::Elephant => 3, // Bishop
-        Piece::Chariot => 4,  // Rook
-        Piece::Cannon => 5,   // Cannon
-        Piece::Advisor => 2,  // Advisor (similar to knight)
-        Piece::General => 6,  // King
+fn see_piece_value(piece: Piece, color: Color, pos: (usize, usize)) -> i32 {
+    let crossed_river = match color {
+        Color::Red => pos.0 <= 4,
+        Color::Black => pos.0 >= 5,
+    };
+    if piece == Piece::Soldier && crossed_river {
+        SEE_CROSSED_SOLDIER_VALUE
+    } else {
+        SEE_PIECE_VALUES[get_piece_value(piece)]
     }
 }
 
-fn compute_hash(board: &Board) -> u64 {
+// Zobrist key of a position; stable across runs, so it also keys the opening book
+pub fn compute_hash(board: &Board) -> u64 {
     let zobrist = get_zobrist();
     let mut hash = 0;
 
@@ -576,19 +1187,30 @@ struct Zobrist {
     side_to_move: u64,
 }
 
+// Keys come from a fixed-seed SplitMix64 stream rather than `rand`, so hashes
+// are identical on every run, machine and rand version
+const ZOBRIST_SEED: u64 = 0x5851_f42d_4c95_7f2d;
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
 impl Zobrist {
     fn new() -> Self {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
+        let mut state = ZOBRIST_SEED;
         let mut z = Zobrist {
             piece_square: [[[0; 90]; 7]; 2],
-            side_to_move: rng.gen(),
+            side_to_move: splitmix64(&mut state),
         };
 
         for color in 0..2 {
             for piece in 0..7 {
                 for square in 0..90 {
-                    z.piece_square[color][piece][square] = rng.gen();
+                    z.piece_square[color][piece][square] = splitmix64(&mut state);
                 }
             }
         }
@@ -0,0 +1,112 @@
+use crate::moves::Move;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NodeType {
+    Exact,
+    LowerBound,
+    UpperBound,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TTEntry {
+    pub depth: i32,
+    pub score: i32,
+    pub node_type: NodeType,
+    pub best_move: Option<Move>,
+}
+
+// One slot of the table. The key is stored xor-ed with the data, so a slot
+// torn by two threads writing at once simply fails the key check on probe
+// instead of handing back a mix of two entries.
+#[derive(Default)]
+struct TTSlot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+// Lock-free transposition table shared by all search threads
+pub struct TranspositionTable {
+    slots: Vec<TTSlot>,
+}
+
+impl TranspositionTable {
+    pub fn new(size_mb: usize) -> Self {
+        let slot_count = (size_mb.max(1) * 1024 * 1024 / std::mem::size_of::<TTSlot>()).max(1);
+        TranspositionTable {
+            slots: (0..slot_count).map(|_| TTSlot::default()).collect(),
+        }
+    }
+
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<TTEntry> {
+        let slot = &self.slots[self.index(hash)];
+        let data = slot.data.load(Ordering::Relaxed);
+        let key = slot.key.load(Ordering::Relaxed);
+        if data == 0 || key ^ data != hash {
+            return None;
+        }
+        Some(unpack(data))
+    }
+
+    pub fn store(&self, hash: u64, entry: TTEntry) {
+        let slot = &self.slots[self.index(hash)];
+        let data = pack(&entry);
+        slot.key.store(hash ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+
+    fn index(&self, hash: u64) -> usize {
+        (hash % self.slots.len() as u64) as usize
+    }
+}
+
+// Layout of the data word (low to high bits):
+//   0..32  score
+//  32..40  depth (clamped to i8)
+//  40..42  node type
+//  42      move present
+//  43..59  move as four 4-bit coordinates
+//  63      always set, so a packed entry is never zero
+fn pack(entry: &TTEntry) -> u64 {
+    let mut data = entry.score as u32 as u64;
+    data |= (entry.depth.clamp(i8::MIN as i32, i8::MAX as i32) as i8 as u8 as u64) << 32;
+    data |= (match entry.node_type {
+        NodeType::Exact => 0u64,
+        NodeType::LowerBound => 1,
+        NodeType::UpperBound => 2,
+    }) << 40;
+    if let Some(mv) = &entry.best_move {
+        let coords = (mv.from.0 as u64) | (mv.from.1 as u64) << 4 | (mv.to.0 as u64) << 8 | (mv.to.1 as u64) << 12;
+        data |= 1 << 42;
+        data |= coords << 43;
+    }
+    data | 1 << 63
+}
+
+fn unpack(data: u64) -> TTEntry {
+    let coords = (data >> 43) & 0xffff;
+    TTEntry {
+        score: data as u32 as i32,
+        depth: ((data >> 32) & 0xff) as u8 as i8 as i32,
+        node_type: match (data >> 40) & 0b11 {
+            0 => NodeType::Exact,
+            1 => NodeType::LowerBound,
+            _ => NodeType::UpperBound,
+        },
+        best_move: if data & (1 << 42) != 0 {
+            Some(Move::new(
+                ((coords & 0xf) as usize, (coords >> 4 & 0xf) as usize),
+                ((coords >> 8 & 0xf) as usize, (coords >> 12 & 0xf) as usize),
+            ))
+        } else {
+            None
+        },
+    }
+}
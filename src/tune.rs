@@ -0,0 +1,102 @@
+use std::fs;
+use chess_engine::tuner::{parse_labelled_position, TuneOptions, Tuner, TuningSet};
+use chess_engine::EvalParams;
+use crate::log::diag;
+
+pub const DEFAULT_TUNED_FILE: &str = "tuned_eval.toml";
+const REPORT_INTERVAL: usize = 50; // iterations between progress lines and saves
+
+pub struct TuneArgs {
+    pub output: String,
+    pub eval_file: Option<String>,
+    pub inputs: Vec<String>,
+    pub options: TuneOptions,
+}
+
+// tune [-o file] [--eval file] [--iterations n] [--rate r] [--k k] <positions...>
+pub fn parse_tune_args(args: &[String]) -> Result<TuneArgs, String> {
+    let mut parsed = TuneArgs {
+        output: DEFAULT_TUNED_FILE.to_string(),
+        eval_file: None,
+        inputs: Vec::new(),
+        options: TuneOptions::default(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", name))
+        };
+        match arg.as_str() {
+            "-o" | "--output" => parsed.output = value(arg)?,
+            "--eval" => parsed.eval_file = Some(value(arg)?),
+            "--iterations" => parsed.options.iterations = parse_number(arg, &value(arg)?)?,
+            "--rate" => parsed.options.learning_rate = parse_number(arg, &value(arg)?)?,
+            "--k" => parsed.options.k = Some(parse_number(arg, &value(arg)?)?),
+            option if option.starts_with('-') => return Err(format!("unknown option {}", option)),
+            path => parsed.inputs.push(path.to_string()),
+        }
+    }
+    if parsed.inputs.is_empty() {
+        return Err("no position files given".to_string());
+    }
+    Ok(parsed)
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid {} value {}", name, value))
+}
+
+// Tunes the weights (the defaults, or those of --eval) on files of labelled
+// positions, one "<fen> <result>" per line, and writes them to the output
+// file every REPORT_INTERVAL iterations and at the end.
+pub fn run_tune(args: TuneArgs) -> Result<(), String> {
+    let params = match &args.eval_file {
+        Some(path) => EvalParams::load(path)?,
+        None => EvalParams::default(),
+    };
+
+    let mut set = TuningSet::new();
+    let mut skipped = 0;
+    for path in &args.inputs {
+        let text = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_labelled_position(line) {
+                Ok((board, result)) => {
+                    if !set.add(&board, result, &params) {
+                        skipped += 1;
+                    }
+                }
+                Err(e) => {
+                    diag!("{}: line {}: {}", path, number + 1, e);
+                    skipped += 1;
+                }
+            }
+        }
+    }
+    if set.is_empty() {
+        return Err("no positions to tune on".to_string());
+    }
+
+    let k = args.options.k.unwrap_or_else(|| set.best_k(&params));
+    println!("{} positions ({} skipped), K = {:.3}, error {:.6}", set.len(), skipped, k, set.error(&params, k));
+
+    let mut tuner = Tuner::new(&set, &params, k, args.options.learning_rate);
+    for iteration in 1..=args.options.iterations {
+        let error = tuner.step();
+        if iteration % REPORT_INTERVAL == 0 || iteration == args.options.iterations {
+            println!("iteration {}: error {:.6}", iteration, error);
+            tuner.params().save(&args.output)?;
+        }
+    }
+
+    let tuned = tuner.params();
+    tuned.save(&args.output)?;
+    println!("error {:.6}, weights written to {}", set.error(&tuned, k), args.output);
+    Ok(())
+}
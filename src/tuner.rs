@@ -0,0 +1,228 @@
+// Texel tuning: fits the evaluation weights to the results of the games the
+// positions were taken from. Each position is first played out to a quiet
+// one by a capture search, then stored as the coefficients of its
+// evaluation, which is linear in the weights. A set of weights predicts a
+// result of sigmoid(K * eval) for Red, and Adam gradient steps lower the mean
+// squared error of those predictions.
+use std::f64::consts::LN_10;
+use std::thread;
+use crate::board::{Board, Color};
+use crate::eval_params::EvalParams;
+use crate::evaluation::{coefficients, MAX_PHASE};
+use crate::search::quiet_position;
+
+const ADAM_BETA1: f64 = 0.9;
+const ADAM_BETA2: f64 = 0.999;
+const ADAM_EPSILON: f64 = 1e-8;
+
+pub struct TuneOptions {
+    pub iterations: usize,
+    pub learning_rate: f64, // largest step of a weight per iteration, roughly
+    pub k: Option<f64>,     // fitted to the starting weights when not given
+}
+
+impl Default for TuneOptions {
+    fn default() -> Self {
+        TuneOptions {
+            iterations: 1000,
+            learning_rate: 1.0,
+            k: None,
+        }
+    }
+}
+
+// A FEN followed by the game result from Red's side: "1-0", "0-1" or
+// "1/2-1/2", or a number in brackets such as [1.0], [0.5] or [0]. The EPD
+// form `<fen> c9 "1-0";` is read as well.
+pub fn parse_labelled_position(line: &str) -> Result<(Board, f64), String> {
+    let line = line.trim().trim_end_matches(';').trim_end();
+    let (fen, label) = line.rsplit_once(char::is_whitespace).ok_or("missing result")?;
+    let result = match label.trim_matches('"') {
+        "1-0" => 1.0,
+        "0-1" => 0.0,
+        "1/2-1/2" => 0.5,
+        _ => label
+            .strip_prefix('[')
+            .and_then(|label| label.strip_suffix(']'))
+            .and_then(|number| number.parse::<f64>().ok())
+            .filter(|result| (0.0..=1.0).contains(result))
+            .ok_or_else(|| format!("invalid result {}", label))?,
+    };
+    let fen = fen.trim_end();
+    let fen = fen.strip_suffix(" c9").unwrap_or(fen);
+    Ok((Board::from_fen(fen)?, result))
+}
+
+struct Sample {
+    result: f64,
+    coefficients: Vec<(usize, f64)>, // non-zero entries of the weight vector
+}
+
+#[derive(Default)]
+pub struct TuningSet {
+    samples: Vec<Sample>,
+}
+
+impl TuningSet {
+    pub fn new() -> Self {
+        TuningSet::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    // Stores a position with its result for Red. Pending captures are played
+    // out with `params` first. A position with the side to move in check is
+    // not added, as a capture search cannot resolve it.
+    pub fn add(&mut self, board: &Board, result: f64, params: &EvalParams) -> bool {
+        let side = if board.red_to_move { Color::Red } else { Color::Black };
+        if board.is_in_check(side) {
+            return false;
+        }
+        // Averaging with the mirror image keeps the tuned tables left-right
+        // symmetric
+        let quiet = quiet_position(board, params);
+        let plain = coefficients(&quiet).to_vector();
+        let mirrored = coefficients(&quiet.mirror_files()).to_vector();
+        let coefficients = plain
+            .iter()
+            .zip(&mirrored)
+            .enumerate()
+            .filter(|(_, (a, b))| **a + **b != 0)
+            .map(|(index, (a, b))| (index, (a + b) as f64 / 2.0))
+            .collect();
+        self.samples.push(Sample { result, coefficients });
+        true
+    }
+
+    // Mean squared difference between the results and the predictions
+    pub fn error(&self, params: &EvalParams, k: f64) -> f64 {
+        let weights = to_weights(params);
+        self.error_and_gradient(&weights, k, false).0
+    }
+
+    // The sigmoid scale that fits the evaluations of `params` best
+    pub fn best_k(&self, params: &EvalParams) -> f64 {
+        let weights = to_weights(params);
+        let error = |k: f64| self.error_and_gradient(&weights, k, false).0;
+        let mut best = (1.0, error(1.0));
+        let mut step = 0.5;
+        for _ in 0..4 {
+            for i in -10..=10 {
+                let k = best.0 + i as f64 * step;
+                if k > 0.0 {
+                    let candidate = error(k);
+                    if candidate < best.1 {
+                        best = (k, candidate);
+                    }
+                }
+            }
+            step /= 10.0;
+        }
+        best.0
+    }
+
+    // The error, and its gradient with respect to each weight when asked for.
+    // Samples are split between threads.
+    fn error_and_gradient(&self, weights: &[f64], k: f64, with_gradient: bool) -> (f64, Vec<f64>) {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk_size = self.samples.len().div_ceil(threads).max(1);
+        let scale = k * LN_10 / 400.0 / MAX_PHASE as f64;
+        let partials: Vec<(f64, Vec<f64>)> = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .samples
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        let mut error = 0.0;
+                        let mut gradient = vec![0.0; if with_gradient { weights.len() } else { 0 }];
+                        for sample in chunk {
+                            let eval: f64 = sample.coefficients.iter().map(|&(i, c)| weights[i] * c).sum();
+                            let prediction = 1.0 / (1.0 + (-eval * scale).exp());
+                            let miss = prediction - sample.result;
+                            error += miss * miss;
+                            if with_gradient {
+                                let slope = 2.0 * miss * prediction * (1.0 - prediction) * scale;
+                                for &(i, c) in &sample.coefficients {
+                                    gradient[i] += slope * c;
+                                }
+                            }
+                        }
+                        (error, gradient)
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+
+        let count = self.samples.len().max(1) as f64;
+        let mut error = 0.0;
+        let mut gradient = vec![0.0; if with_gradient { weights.len() } else { 0 }];
+        for (partial_error, partial_gradient) in partials {
+            error += partial_error;
+            for (total, part) in gradient.iter_mut().zip(partial_gradient) {
+                *total += part;
+            }
+        }
+        gradient.iter_mut().for_each(|g| *g /= count);
+        (error / count, gradient)
+    }
+}
+
+// Adam gradient descent over the flat weight vector of EvalParams
+pub struct Tuner<'a> {
+    set: &'a TuningSet,
+    k: f64,
+    learning_rate: f64,
+    weights: Vec<f64>,
+    moments: Vec<f64>,
+    squares: Vec<f64>,
+    steps: i32,
+}
+
+impl<'a> Tuner<'a> {
+    pub fn new(set: &'a TuningSet, start: &EvalParams, k: f64, learning_rate: f64) -> Self {
+        let weights = to_weights(start);
+        let count = weights.len();
+        Tuner {
+            set,
+            k,
+            learning_rate,
+            weights,
+            moments: vec![0.0; count],
+            squares: vec![0.0; count],
+            steps: 0,
+        }
+    }
+
+    // Takes one step and returns the error before it
+    pub fn step(&mut self) -> f64 {
+        let (error, gradient) = self.set.error_and_gradient(&self.weights, self.k, true);
+        self.steps += 1;
+        let moment_scale = 1.0 - ADAM_BETA1.powi(self.steps);
+        let square_scale = 1.0 - ADAM_BETA2.powi(self.steps);
+        for (i, g) in gradient.into_iter().enumerate() {
+            self.moments[i] = ADAM_BETA1 * self.moments[i] + (1.0 - ADAM_BETA1) * g;
+            self.squares[i] = ADAM_BETA2 * self.squares[i] + (1.0 - ADAM_BETA2) * g * g;
+            let moment = self.moments[i] / moment_scale;
+            let square = self.squares[i] / square_scale;
+            self.weights[i] -= self.learning_rate * moment / (square.sqrt() + ADAM_EPSILON);
+        }
+        error
+    }
+
+    // The current weights, rounded
+    pub fn params(&self) -> EvalParams {
+        let values: Vec<i32> = self.weights.iter().map(|w| w.round() as i32).collect();
+        EvalParams::from_vector(&values).expect("the tuner keeps the vector length")
+    }
+}
+
+fn to_weights(params: &EvalParams) -> Vec<f64> {
+    params.to_vector().into_iter().map(f64::from).collect()
+}
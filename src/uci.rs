@@ -1,10 +1,33 @@
-use std::io::{self, BufRead, Write};
-use crate::board::Board;
-use crate::search::find_best_move;
+use std::io::{self, BufRead};
+use chess_engine::book::{Book, MAX_VARIETY};
+use chess_engine::eval_params::EvalParams;
+use chess_engine::search::{mate_in, DEFAULT_HASH_MB};
+use chess_engine::{
+    generate_legal_moves, trace_evaluation, Board, IterationReport, Move, ScoreBound, SearchLimits, SearchObserver,
+    SearchResult, Searcher,
+};
+use crate::bench::{parse_bench_args, run_bench};
+use crate::log::{self, diag, send};
+
+const MAX_HASH_MB: usize = 1024;
+const MAX_THREADS: usize = 64;
+
+// How the UCI loop ended
+#[derive(PartialEq)]
+pub enum Handoff {
+    Quit,
+    XBoard, // the GUI spoke CECP, continue with the XBoard front end
+}
 
 pub struct UCIEngine {
     board: Board,
     running: bool,
+    searcher: Searcher,
+    threads: usize,
+    deterministic: bool,
+    own_book: bool,
+    book: Option<Book>,
+    book_variety: u32,
 }
 
 impl UCIEngine {
@@ -12,15 +35,97 @@ impl UCIEngine {
         UCIEngine {
             board: Board::new(),
             running: true,
+            searcher: Searcher::new(DEFAULT_HASH_MB, 1),
+            threads: 1,
+            deterministic: false,
+            own_book: true,
+            book: None,
+            book_variety: 0,
+        }
+    }
+
+    fn process_setoption(&mut self, tokens: &[String]) {
+        // setoption name <id> [value <x>]; option names may contain spaces
+        let name_pos = match tokens.iter().position(|t| t == "name") {
+            Some(pos) => pos,
+            None => {
+                diag!("Error: setoption requires a name");
+                return;
+            }
+        };
+        let value_pos = tokens.iter().position(|t| t == "value");
+        let name = tokens[name_pos + 1..value_pos.unwrap_or(tokens.len())].join(" ");
+        let value = value_pos.map(|pos| tokens[pos + 1..].join(" ")).unwrap_or_default();
+
+        match name.to_lowercase().as_str() {
+            "hash" => match value.parse::<usize>() {
+                Ok(size_mb) => self.searcher.set_hash_size(size_mb.clamp(1, MAX_HASH_MB)),
+                Err(_) => diag!("Error: invalid Hash value {}", value),
+            },
+            "threads" => match value.parse::<usize>() {
+                Ok(threads) => self.threads = threads.clamp(1, MAX_THREADS),
+                Err(_) => diag!("Error: invalid Threads value {}", value),
+            },
+            "deterministic" => self.deterministic = value.eq_ignore_ascii_case("true"),
+            "ownbook" => self.own_book = value.eq_ignore_ascii_case("true"),
+            "bookfile" => {
+                self.book = None;
+                if !value.is_empty() && value != "<empty>" {
+                    match Book::load(&value) {
+                        Ok(book) => {
+                            diag!("Loaded opening book {} with {} entries", value, book.len());
+                            self.book = Some(book);
+                        }
+                        Err(e) => diag!("Error: {}", e),
+                    }
+                }
+            }
+            "bookvariety" => match value.parse::<u32>() {
+                Ok(variety) => self.book_variety = variety.min(MAX_VARIETY),
+                Err(_) => diag!("Error: invalid BookVariety value {}", value),
+            },
+            "evalfile" => {
+                let params = if value.is_empty() || value == "<empty>" {
+                    Ok(EvalParams::default())
+                } else {
+                    EvalParams::load(&value)
+                };
+                match params {
+                    Ok(params) => self.searcher.set_eval_params(params),
+                    Err(e) => diag!("Error: {}", e),
+                }
+            }
+            "evalparam" => {
+                if let Err(e) = self.set_eval_param(&value) {
+                    diag!("Error: {}", e);
+                }
+            }
+            "debug log file" => {
+                if let Err(e) = log::set_log_file(&value) {
+                    diag!("Error: {}", e);
+                }
+            }
+            _ => {}
         }
     }
 
+    // "<name> <value> [<endgame value>]", e.g. "chariot.value 610 630"
+    fn set_eval_param(&mut self, value: &str) -> Result<(), String> {
+        let mut parts = value.split_whitespace();
+        let name = parts.next().ok_or("EvalParam needs a name and a value")?;
+        let numbers = parts
+            .map(|number| number.parse::<i32>().map_err(|_| format!("invalid number {}", number)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut params = self.searcher.eval_params().clone();
+        params.set(name, &numbers)?;
+        self.searcher.set_eval_params(params);
+        Ok(())
+    }
+
     fn process_position(&mut self, tokens: &[String]) {
         if tokens.len() < 2 {
-            println!("Error: position command requires more arguments");
-            println!("Usage: position startpos");
-            println!("       position startpos moves e2e4 e7e5 ...");
-            println!("       position fen <fenstring>");
+            diag!("Error: position command requires more arguments");
+            diag!("Usage: position startpos [moves <move1> ...] | position fen <fen> [moves <move1> ...]");
             return;
         }
         
@@ -31,137 +136,194 @@ impl UCIEngine {
                     match Board::from_fen(&fen) {
                         Ok(new_board) => {
                             self.board = new_board;
-                            println!("Position set from FEN successfully");
-                            
+
                             // apply any moves after the FEN if present
                             if tokens.len() > 9 && tokens[8] == "moves" {
-                                println!("Applying moves: {:?}", &tokens[9..]);
-                                for move_str in tokens[9..].iter() {
-                                    let from = (
-                                        (move_str.chars().nth(1).unwrap() as u8 - b'0') as usize,
-                                        (move_str.chars().nth(0).unwrap() as u8 - b'a') as usize,
-                                    );
-                                    let to = (
-                                        (move_str.chars().nth(3).unwrap() as u8 - b'0') as usize,
-                                        (move_str.chars().nth(2).unwrap() as u8 - b'a') as usize,
-                                    );
-                                    self.board.make_move(from, to);
-                                }
+                                self.apply_moves(&tokens[9..]);
                             }
                         }
-                        Err(e) => println!("Error parsing FEN: {}", e),
+                        Err(e) => diag!("Error parsing FEN: {}", e),
                     }
                 } else {
-                    println!("Error: Invalid FEN string - not enough parts");
-                    println!("Usage: position fen <fen_parts> [moves <move1> <move2> ...]");
-                    println!("FEN should have 6 parts: position pieces active_color castling en_passant halfmove fullmove");
+                    diag!("Error: Invalid FEN string - not enough parts");
+                    diag!("Usage: position fen <fen_parts> [moves <move1> <move2> ...]");
                 }
             }
             "startpos" => {
-                println!("Setting up initial position...");
                 self.board.setup_initial_position();
                 if tokens.len() > 3 && tokens[2] == "moves" {
-                    println!("Applying moves: {:?}", &tokens[3..]);
-                    // starting with moves if haved
-                    for move_str in tokens[3..].iter() {
-                        let from = (
-                            (move_str.chars().nth(1).unwrap() as u8 - b'0') as usize,
-                            (move_str.chars().nth(0).unwrap() as u8 - b'a') as usize,
-                        );
-                        let to = (
-                            (move_str.chars().nth(3).unwrap() as u8 - b'0') as usize,
-                            (move_str.chars().nth(2).unwrap() as u8 - b'a') as usize,
-                        );
-                        if !self.board.make_move(from, to) {
-                            println!("Error: Invalid move {}", move_str);
-                            break;
-                        }
-                    }
+                    self.apply_moves(&tokens[3..]);
                 }
-                // show current board
-                //println!("\nCurrent position:");
-                // println!("{}", self.board);
             }
             _ => {
-                println!("Error: Unknown position subcommand");
-                println!("Usage: position startpos");
-                println!("       position startpos moves e2e4 e7e5 ...");
-                println!("       position fen <fenstring>");
+                diag!("Error: Unknown position subcommand");
+                diag!("Usage: position startpos [moves <move1> ...] | position fen <fen> [moves <move1> ...]");
             }
         }
-        io::stdout().flush().unwrap();
     }
 
-    fn process_go(&self) {
-        println!("Calculating best move...");
-        if let Some(best_move) = find_best_move(&self.board) {
-            println!("bestmove {}", best_move);
-        } else {
-            println!("bestmove none");
+    fn apply_moves(&mut self, moves: &[String]) {
+        for move_str in moves {
+            let legal = Move::from_uci(move_str)
+                .filter(|mv| generate_legal_moves(&self.board).contains(mv))
+                .is_some_and(|mv| self.board.make_move(mv.from, mv.to));
+            if !legal {
+                diag!("Error: Invalid move {}", move_str);
+                break;
+            }
+        }
+    }
+
+    // A move from the opening book, if the book is enabled and knows the
+    // position. Deterministic mode always takes the heaviest move.
+    fn book_move(&self) -> Option<Move> {
+        let book = self.book.as_ref().filter(|_| self.own_book)?;
+        let variety = if self.deterministic { 0 } else { self.book_variety };
+        book.choose(&self.board, variety, &mut rand::thread_rng())
+    }
+
+    fn process_go(&mut self, tokens: &[String]) {
+        if let Some(mv) = self.book_move() {
+            diag!("book move {}", mv);
+            send!("bestmove {}", mv);
+            return;
         }
-        io::stdout().flush().unwrap();
+
+        let limits = parse_go_limits(tokens);
+        self.searcher.set_threads(self.threads);
+        self.searcher.set_deterministic(self.deterministic);
+
+        let result = self.searcher.search(&self.board, &limits, &mut UciObserver);
+        log::log_note(&format!(
+            "search finished: depth {} score {} nodes {} time {} nps {} bestmove {}",
+            result.depth,
+            format_score(result.score, result.bound),
+            result.nodes,
+            result.time_ms,
+            result.nodes * 1000 / result.time_ms.max(1),
+            result.best_move.map_or("none".to_string(), |mv| mv.to_uci())
+        ));
     }
 
-    pub fn main_loop(&mut self) {
+    pub fn main_loop(&mut self) -> Handoff {
         let stdin = io::stdin();
-        let mut stdout = io::stdout();
         
-        for line in stdin.lock().lines() {
-            if let Ok(input) = line {
-                let tokens: Vec<String> = input
-                    .split_whitespace()
-                    .map(String::from)
-                    .collect();
-                
-                if tokens.is_empty() {
-                    continue;
-                }
+        for input in stdin.lock().lines().map_while(Result::ok) {
+            log::log_input(&input);
+            let tokens: Vec<String> = input
+                .split_whitespace()
+                .map(String::from)
+                .collect();
+            
+            if tokens.is_empty() {
+                continue;
+            }
 
-                println!("Received command: {}", tokens[0]);
-                match tokens[0].as_str() {
-                    "uci" => {
-                        println!("id name XiangqiEngine");
-                        println!("id author Hien Duc");
-                        println!("option name Hash type spin default 16 min 1 max 1024");
-                        println!("option name Style type combo default normal var solid var normal var risky");
-                        println!("uciok");
-                        stdout.flush().unwrap();
-                    }
-                    "isready" => {
-                        println!("readyok");
-                        stdout.flush().unwrap();
-                    }
-                    "position" => self.process_position(&tokens),
-                    "go" => self.process_go(),
-                    "ucinewgame" => {
-                        self.board = Board::new();
-                        println!("info string New game started");
-                        stdout.flush().unwrap();
-                    },
-                    "quit" => {
-                        println!("Goodbye!");
-                        self.running = false;
-                        break;
-                    }
-                    // "d" | "display" => {
-                    //     println!("\nCurrent position:");
-                    //     println!("{}", self.board);
-                    //     stdout.flush().unwrap();
-                    // }
-                    _ => {
-                        println!("Unknown command: {}", tokens[0]);
-                        println!("Available commands:");
-                        println!("  uci        - Initialize the engine");
-                        println!("  isready    - Check if engine is ready");
-                        println!("  ucinewgame - Reset the engine state for a new game");
-                        println!("  position   - Set up a position");
-                        println!("  go         - Start calculating");
-                        // println!("  d          - Display current position");
-                        println!("  quit       - Exit the engine");
-                        stdout.flush().unwrap();
+            match tokens[0].as_str() {
+                "uci" => {
+                    send!("id name XiangqiEngine");
+                    send!("id author Hien Duc");
+                    send!("option name Hash type spin default {} min 1 max {}", DEFAULT_HASH_MB, MAX_HASH_MB);
+                    send!("option name Threads type spin default 1 min 1 max {}", MAX_THREADS);
+                    send!("option name Deterministic type check default false");
+                    send!("option name OwnBook type check default true");
+                    send!("option name BookFile type string default <empty>");
+                    send!("option name BookVariety type spin default 0 min 0 max {}", MAX_VARIETY);
+                    send!("option name EvalFile type string default <empty>");
+                    send!("option name EvalParam type string default <empty>");
+                    send!("option name Debug Log File type string default <empty>");
+                    send!("option name Style type combo default normal var solid var normal var risky");
+                    send!("uciok");
+                }
+                "isready" => {
+                    send!("readyok");
+                }
+                "debug" => log::set_debug(tokens.get(1).is_some_and(|t| t == "on")),
+                "setoption" => self.process_setoption(&tokens),
+                "position" => self.process_position(&tokens),
+                "go" => self.process_go(&tokens),
+                "eval" => {
+                    let trace = trace_evaluation(&self.board, self.searcher.eval_params());
+                    for line in trace.to_string().lines() {
+                        send!("{}", line);
                     }
                 }
+                "bench" => {
+                    let (depth, threads, hash_mb) = parse_bench_args(&tokens[1..]);
+                    run_bench(depth, threads, hash_mb, true);
+                }
+                "ucinewgame" => {
+                    self.board = Board::new();
+                    self.searcher.clear();
+                },
+                "xboard" => return Handoff::XBoard,
+                "quit" => {
+                    self.running = false;
+                    break;
+                }
+                _ => {
+                    diag!("Unknown command: {}", tokens[0]);
+                    diag!("Available commands: uci, xboard, debug, isready, setoption, ucinewgame, position, go, eval, bench, quit");
+                }
+            }
+        }
+        Handoff::Quit
+    }
+}
+
+// Formats search progress and the final result as UCI output
+struct UciObserver;
+
+impl SearchObserver for UciObserver {
+    fn on_iteration(&mut self, report: &IterationReport) {
+        let pv: Vec<String> = report.pv.iter().map(|mv| mv.to_uci()).collect();
+        send!(
+            "info depth {} score {} nodes {} time {} pv {}",
+            report.depth,
+            format_score(report.score, report.bound),
+            report.nodes,
+            report.time_ms,
+            pv.join(" ")
+        );
+    }
+
+    fn on_result(&mut self, result: &SearchResult) {
+        match (&result.best_move, &result.ponder_move) {
+            (Some(best_move), Some(ponder_move)) => send!("bestmove {} ponder {}", best_move, ponder_move),
+            (Some(best_move), None) => send!("bestmove {}", best_move),
+            (None, _) => send!("bestmove none"),
+        }
+    }
+}
+
+fn format_score(score: i32, bound: ScoreBound) -> String {
+    let value = match mate_in(score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", score),
+    };
+    match bound {
+        ScoreBound::Exact => value,
+        ScoreBound::Lower => format!("{} lowerbound", value),
+        ScoreBound::Upper => format!("{} upperbound", value),
+    }
+}
+
+// go [movetime <ms>] [nodes <n>] [depth <d>]
+fn parse_go_limits(tokens: &[String]) -> SearchLimits {
+    let mut limits = SearchLimits::default();
+    let mut i = 1;
+    while i < tokens.len() {
+        let value = tokens.get(i + 1);
+        match tokens[i].as_str() {
+            "movetime" => limits.time_limit = value.and_then(|v| v.parse().ok()),
+            "nodes" => limits.nodes = value.and_then(|v| v.parse().ok()),
+            "depth" => limits.depth = value.and_then(|v| v.parse().ok()),
+            _ => {
+                i += 1;
+                continue;
             }
         }
+        i += 2;
     }
-}
\ No newline at end of file
+    limits
+}
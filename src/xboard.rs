@@ -0,0 +1,276 @@
+use std::io::{self, BufRead};
+use chess_engine::search::{mate_in, DEFAULT_HASH_MB};
+use chess_engine::{
+    generate_legal_moves, has_legal_move, Board, Color, IterationReport, Move, ScoreBound,
+    SearchLimits, SearchObserver, Searcher,
+};
+use crate::log::{self, diag, send};
+
+const MAX_HASH_MB: usize = 1024;
+const MAX_THREADS: usize = 64;
+const DEFAULT_MOVES_TO_GO: u64 = 30; // assumed moves left when the time control has no session length
+const TIME_SAFETY_MS: u64 = 50; // never plan to use the last few milliseconds on the clock
+const MIN_THINK_MS: u64 = 10;
+const XBOARD_MATE_SCORE: i32 = 100_000; // CECP convention: mate in N is reported as 100000 + N
+
+// CECP (XBoard/WinBoard) front end. Only `variant xiangqi` is supported;
+// squares use the same a0..i9 coordinates as the UCI front end.
+pub struct XBoardEngine {
+    board: Board,
+    history: Vec<Board>,
+    searcher: Searcher,
+    engine_color: Option<Color>, // None in force mode
+    post: bool,
+    max_depth: Option<i32>,
+    move_time_ms: Option<u64>,
+    moves_per_session: u64,
+    increment_ms: u64,
+    time_left_ms: Option<u64>,
+}
+
+impl XBoardEngine {
+    pub fn new() -> Self {
+        let mut board = Board::new();
+        board.setup_initial_position();
+        XBoardEngine {
+            board,
+            history: Vec::new(),
+            searcher: Searcher::new(DEFAULT_HASH_MB, 1),
+            engine_color: Some(Color::Black),
+            post: false,
+            max_depth: None,
+            move_time_ms: None,
+            moves_per_session: 0,
+            increment_ms: 0,
+            time_left_ms: None,
+        }
+    }
+
+    pub fn main_loop(&mut self) {
+        let stdin = io::stdin();
+
+        for input in stdin.lock().lines().map_while(Result::ok) {
+            log::log_input(&input);
+            let tokens: Vec<String> = input
+                .split_whitespace()
+                .map(String::from)
+                .collect();
+
+            if tokens.is_empty() {
+                continue;
+            }
+
+            let arg = tokens.get(1).map(String::as_str).unwrap_or("");
+            match tokens[0].as_str() {
+                "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer"
+                | "name" | "rating" | "ics" | "draw" | "?" | "otim" => {
+                    // nothing to do: the search is synchronous, and the
+                    // opponent's clock does not affect time allocation
+                }
+                "protover" => {
+                    send!(
+                        "feature myname=\"XiangqiEngine\" variants=\"xiangqi\" setboard=1 usermove=1 \
+                         ping=1 playother=0 colors=0 time=1 sigint=0 sigterm=0 reuse=1 analyze=0 \
+                         memory=1 smp=1 debug=1 done=1"
+                    );
+                }
+                "new" => self.new_game(),
+                "variant" => {
+                    if arg != "xiangqi" {
+                        send!("Error (unsupported variant): {}", arg);
+                    }
+                }
+                "force" | "result" => self.engine_color = None,
+                "go" => {
+                    self.engine_color = Some(self.side_to_move());
+                    self.think_and_move();
+                }
+                "usermove" => self.user_move(arg),
+                "level" => self.process_level(&tokens),
+                "st" => match arg.parse::<u64>() {
+                    Ok(seconds) => self.move_time_ms = Some(seconds * 1000),
+                    Err(_) => send!("Error (bad st value): {}", arg),
+                },
+                "sd" => match arg.parse::<i32>() {
+                    Ok(depth) => self.max_depth = Some(depth.max(1)),
+                    Err(_) => send!("Error (bad sd value): {}", arg),
+                },
+                "time" => match arg.parse::<u64>() {
+                    Ok(centiseconds) => self.time_left_ms = Some(centiseconds * 10),
+                    Err(_) => send!("Error (bad time value): {}", arg),
+                },
+                "undo" => self.take_back(1),
+                "remove" => self.take_back(2),
+                "setboard" => match Board::from_fen(&tokens[1..].join(" ")) {
+                    Ok(board) => {
+                        self.board = board;
+                        self.history.clear();
+                    }
+                    Err(e) => send!("tellusererror Illegal position: {}", e),
+                },
+                "ping" => send!("pong {}", arg),
+                "post" => self.post = true,
+                "nopost" => self.post = false,
+                "memory" => match arg.parse::<usize>() {
+                    Ok(size_mb) => self.searcher.set_hash_size(size_mb.clamp(1, MAX_HASH_MB)),
+                    Err(_) => send!("Error (bad memory value): {}", arg),
+                },
+                "cores" => match arg.parse::<usize>() {
+                    Ok(threads) => self.searcher.set_threads(threads.clamp(1, MAX_THREADS)),
+                    Err(_) => send!("Error (bad cores value): {}", arg),
+                },
+                "quit" => break,
+                // tolerate GUIs that send bare moves without the usermove prefix
+                command if Move::from_uci(command).is_some() => self.user_move(command),
+                command => send!("Error (unknown command): {}", command),
+            }
+        }
+    }
+
+    fn new_game(&mut self) {
+        self.board.setup_initial_position();
+        self.history.clear();
+        self.searcher.clear();
+        self.engine_color = Some(Color::Black);
+        self.max_depth = None;
+        self.move_time_ms = None;
+    }
+
+    fn side_to_move(&self) -> Color {
+        if self.board.red_to_move {
+            Color::Red
+        } else {
+            Color::Black
+        }
+    }
+
+    fn user_move(&mut self, move_str: &str) {
+        let legal = Move::from_uci(move_str)
+            .filter(|mv| generate_legal_moves(&self.board).contains(mv))
+            .filter(|mv| self.board.clone().make_move(mv.from, mv.to));
+        match legal {
+            Some(mv) => {
+                self.play(&mv);
+                // in force mode the GUI is only replaying moves, so claim nothing
+                if self.engine_color == Some(self.side_to_move()) {
+                    self.think_and_move();
+                } else if self.engine_color.is_some() {
+                    self.check_game_over();
+                }
+            }
+            None => send!("Illegal move: {}", move_str),
+        }
+    }
+
+    fn play(&mut self, mv: &Move) {
+        self.history.push(self.board.clone());
+        self.board.make_move(mv.from, mv.to);
+    }
+
+    fn take_back(&mut self, plies: usize) {
+        for _ in 0..plies {
+            match self.history.pop() {
+                Some(board) => self.board = board,
+                None => {
+                    diag!("Error: no move to take back");
+                    break;
+                }
+            }
+        }
+    }
+
+    // Reports the result and leaves the engine idle if the side to move has no legal move
+    fn check_game_over(&mut self) -> bool {
+        if has_legal_move(&self.board) {
+            return false;
+        }
+        match self.side_to_move() {
+            Color::Red => send!("0-1 {{Black wins}}"),
+            Color::Black => send!("1-0 {{Red wins}}"),
+        }
+        self.engine_color = None;
+        true
+    }
+
+    fn think_and_move(&mut self) {
+        if self.check_game_over() {
+            return;
+        }
+        let limits = SearchLimits {
+            time_limit: self.think_time(),
+            depth: self.max_depth,
+            ..Default::default()
+        };
+        let result = self.searcher.search(&self.board, &limits, &mut XBoardObserver { post: self.post });
+        log::log_note(&format!(
+            "search finished: depth {} score {} nodes {} time {}",
+            result.depth, result.score, result.nodes, result.time_ms
+        ));
+        match result.best_move {
+            Some(mv) => {
+                send!("move {}", mv);
+                self.play(&mv);
+                self.check_game_over();
+            }
+            None => diag!("Error: search returned no move"),
+        }
+    }
+
+    // Time for the next move from `st`, or from the clock and the `level` settings
+    fn think_time(&self) -> Option<u64> {
+        if let Some(move_time) = self.move_time_ms {
+            return Some(move_time.saturating_sub(TIME_SAFETY_MS).max(MIN_THINK_MS));
+        }
+        let time_left = self.time_left_ms?;
+        let moves_to_go = if self.moves_per_session > 0 {
+            let moves_made = self.history.len() as u64 / 2;
+            self.moves_per_session - moves_made % self.moves_per_session
+        } else {
+            DEFAULT_MOVES_TO_GO
+        };
+        let budget = time_left / moves_to_go + self.increment_ms * 3 / 4;
+        Some(budget.min(time_left.saturating_sub(TIME_SAFETY_MS)).max(MIN_THINK_MS))
+    }
+
+    // level <moves per session> <base as minutes or min:sec> <increment in seconds>;
+    // the base itself is not needed because the GUI sends `time` before each move
+    fn process_level(&mut self, tokens: &[String]) {
+        if tokens.len() < 4 {
+            send!("Error (bad level): {}", tokens.join(" "));
+            return;
+        }
+        self.moves_per_session = tokens[1].parse().unwrap_or(0);
+        self.increment_ms = tokens[3]
+            .parse::<f64>()
+            .map(|seconds| (seconds * 1000.0) as u64)
+            .unwrap_or(0);
+        self.move_time_ms = None;
+    }
+}
+
+// Sends thinking output in the CECP `ply score time nodes pv` format
+struct XBoardObserver {
+    post: bool,
+}
+
+impl SearchObserver for XBoardObserver {
+    fn on_iteration(&mut self, report: &IterationReport) {
+        if !self.post || report.bound != ScoreBound::Exact {
+            return;
+        }
+        let score = match mate_in(report.score) {
+            Some(moves) if moves > 0 => XBOARD_MATE_SCORE + moves,
+            Some(moves) => -XBOARD_MATE_SCORE + moves,
+            None => report.score,
+        };
+        let pv: Vec<String> = report.pv.iter().map(|mv| mv.to_uci()).collect();
+        send!(
+            "{} {} {} {} {}",
+            report.depth,
+            score,
+            report.time_ms / 10,
+            report.nodes,
+            pv.join(" ")
+        );
+    }
+}
@@ -0,0 +1,278 @@
+// XQF, the binary format of XQStudio in which most historical Xiangqi
+// collections are stored. A 1024-byte header holds the piece placement,
+// the result and GBK-encoded Pascal strings (event, players, ...). It is
+// followed by the move tree in preorder, one record per node, the first
+// record standing for the start position itself.
+//
+// From version 11 on, the placement and everything after the header are
+// obfuscated: keys derived from header bytes 3 and 8-15 offset the piece
+// squares, the move squares and the comment lengths, and each byte is
+// shifted by a 32-byte key stream cut from a copyright string.
+use encoding_rs::GBK;
+use crate::board::{Board, Color, Piece};
+use crate::game::{Game, ROOT};
+use crate::moves::{generate_legal_moves, Move};
+
+const HEADER_SIZE: usize = 1024;
+const ENCRYPTED_VERSION: u8 = 11;
+const SHUFFLED_VERSION: u8 = 12; // placement slots are also rotated
+const KEY_STREAM_SOURCE: &[u8; 32] = b"[(C) Copyright Mr. Dong Shiwei.]";
+
+// Piece in each of the 16 placement slots of a side
+const SLOT_PIECES: [Piece; 16] = [
+    Piece::Chariot,
+    Piece::Horse,
+    Piece::Elephant,
+    Piece::Advisor,
+    Piece::General,
+    Piece::Advisor,
+    Piece::Elephant,
+    Piece::Horse,
+    Piece::Chariot,
+    Piece::Cannon,
+    Piece::Cannon,
+    Piece::Soldier,
+    Piece::Soldier,
+    Piece::Soldier,
+    Piece::Soldier,
+    Piece::Soldier,
+];
+
+// Header strings: tag, offset of the length byte, maximum length
+const HEADER_STRINGS: [(&str, usize, usize); 8] = [
+    ("Title", 80, 63),
+    ("Event", 208, 63),
+    ("Date", 272, 15),
+    ("Site", 288, 15),
+    ("Red", 304, 15),
+    ("Black", 320, 15),
+    ("TimeControl", 336, 63),
+    ("Annotator", 464, 15),
+];
+
+#[derive(Default)]
+struct Keys {
+    square: u8,
+    from: u8,
+    to: u8,
+    comment: u32,
+    stream: [u8; 32],
+}
+
+impl Keys {
+    fn new(header: &[u8]) -> Self {
+        let version = header[2];
+        if version < ENCRYPTED_VERSION {
+            return Keys::default();
+        }
+        let square = square_54_plus_221(header[13]).wrapping_mul(header[13]);
+        let from = square_54_plus_221(header[14]).wrapping_mul(square);
+        let to = square_54_plus_221(header[15]).wrapping_mul(from);
+        // the comment key and the key stream use the raw header bytes 12-15,
+        // not the offsets derived from them above
+        let comment = (header[12] as u32 * 256 + header[13] as u32) % 32000 + 767;
+
+        let mask = header[3];
+        let parts: [u8; 4] = std::array::from_fn(|i| header[8 + i] | (header[12 + i] & mask));
+        let mut stream = [0u8; 32];
+        for (i, byte) in stream.iter_mut().enumerate() {
+            *byte = KEY_STREAM_SOURCE[i] & parts[i % 4];
+        }
+        Keys { square, from, to, comment, stream }
+    }
+}
+
+fn square_54_plus_221(x: u8) -> u8 {
+    (x as u32 * x as u32 * 54 + 221) as u8
+}
+
+// One node of the move tree as stored in the file
+struct Record {
+    from: u8,
+    to: u8,
+    has_child: bool,
+    has_sibling: bool,
+    comment: Option<String>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    version: u8,
+    keys: Keys,
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, len: usize) -> Result<Vec<u8>, String> {
+        let end = self.pos + len;
+        if end > self.data.len() {
+            return Err("XQF file is truncated".to_string());
+        }
+        let bytes = (self.pos..end)
+            .map(|i| self.data[i].wrapping_sub(self.keys.stream[i % 32]))
+            .collect();
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn record(&mut self) -> Result<Record, String> {
+        let step = self.bytes(4)?;
+        let tag = step[2];
+        let (has_child, has_sibling, has_comment) = if self.version < ENCRYPTED_VERSION {
+            (tag & 0xf0 != 0, tag & 0x0f != 0, true)
+        } else {
+            (tag & 0x80 != 0, tag & 0x40 != 0, tag & 0x20 != 0)
+        };
+
+        let comment = if has_comment {
+            let length = self.bytes(4)?;
+            let length = u32::from_le_bytes([length[0], length[1], length[2], length[3]]);
+            let length = length.wrapping_sub(if self.version < ENCRYPTED_VERSION { 0 } else { self.keys.comment });
+            if length as usize > self.data.len() {
+                return Err("XQF comment length is out of range".to_string());
+            }
+            let text = decode_gbk(&self.bytes(length as usize)?);
+            (!text.is_empty()).then_some(text)
+        } else {
+            None
+        };
+
+        Ok(Record {
+            from: step[0].wrapping_sub(24).wrapping_sub(self.keys.from),
+            to: step[1].wrapping_sub(32).wrapping_sub(self.keys.to),
+            has_child,
+            has_sibling,
+            comment,
+        })
+    }
+}
+
+// Reads one XQF game; every move is checked against its position
+pub fn read_xqf(data: &[u8]) -> Result<Game, String> {
+    if data.len() < HEADER_SIZE || &data[0..2] != b"XQ" {
+        return Err("Not an XQF file".to_string());
+    }
+    let header = &data[..HEADER_SIZE];
+    let version = header[2];
+    let keys = Keys::new(header);
+
+    let mut start = start_position(header, &keys)?;
+    let mut reader = Reader {
+        data,
+        pos: HEADER_SIZE,
+        version,
+        keys,
+    };
+
+    // Walk the preorder records first, noting each one's parent record
+    let root = reader.record()?;
+    let mut records: Vec<(usize, Record)> = Vec::new();
+    let mut parent_of_next = root.has_child.then_some(0);
+    let mut pending_siblings: Vec<usize> = Vec::new();
+    while let Some(parent) = parent_of_next.take().or_else(|| pending_siblings.pop()) {
+        let record = reader.record()?;
+        let index = records.len() + 1;
+        if record.has_sibling {
+            pending_siblings.push(parent);
+        }
+        if record.has_child {
+            parent_of_next = Some(index);
+        }
+        records.push((parent, record));
+    }
+
+    // The side to move is whoever plays the first move, else the header's flag
+    start.red_to_move = header[50] == 0;
+    if let Some((_, first)) = records.first() {
+        let from = square(first.from)?;
+        if let Some((color, _)) = start.squares[from.0][from.1].piece {
+            start.red_to_move = color == Color::Red;
+        }
+    }
+
+    let mut game = Game::new(start);
+    let mut nodes = vec![ROOT]; // game node of each record
+    for (i, (parent, record)) in records.into_iter().enumerate() {
+        let parent = nodes[parent];
+        let mv = Move::new(square(record.from)?, square(record.to)?);
+        let board = game.board_at(parent);
+        if !generate_legal_moves(&board).contains(&mv) || !board.clone().make_move(mv.from, mv.to) {
+            return Err(format!("XQF move {}: illegal move {}", i + 1, mv));
+        }
+        let node = game.add_move(parent, mv);
+        if record.comment.is_some() {
+            game.nodes[node].comment = record.comment;
+        }
+        nodes.push(node);
+    }
+    game.nodes[ROOT].comment = root.comment;
+
+    game.set_header("Game", "Chinese Chess");
+    game.set_header("Variant", "xiangqi");
+    for (tag, offset, max_len) in HEADER_STRINGS {
+        let text = pascal_string(header, offset, max_len);
+        if !text.is_empty() {
+            game.set_header(tag, &text);
+        }
+    }
+    let result = match header[51] {
+        1 => "1-0",
+        2 => "0-1",
+        3 => "1/2-1/2",
+        _ => "*",
+    };
+    game.set_header("Result", result);
+    let mut initial = Board::new();
+    initial.setup_initial_position();
+    if game.start.to_fen() != initial.to_fen() {
+        let fen = game.start.to_fen();
+        game.set_header("FEN", &fen);
+    }
+    Ok(game)
+}
+
+// The 32 placement slots: Red's pieces, then Black's, each byte file * 10 +
+// rank counted from Red's side, or anything off the board for a captured piece
+fn start_position(header: &[u8], keys: &Keys) -> Result<Board, String> {
+    let version = header[2];
+    let mut slots = [0u8; 32];
+    for (i, &byte) in header[16..48].iter().enumerate() {
+        let slot = if version >= SHUFFLED_VERSION {
+            (i + keys.square as usize + 1) & 31
+        } else {
+            i
+        };
+        slots[slot] = byte;
+    }
+
+    let mut board = Board::new();
+    for (i, &byte) in slots.iter().enumerate() {
+        let position = byte.wrapping_sub(keys.square);
+        if position >= 90 {
+            continue;
+        }
+        let (rank, file) = square(position)?;
+        let color = if i < 16 { Color::Red } else { Color::Black };
+        board.squares[rank][file].piece = Some((color, SLOT_PIECES[i % 16]));
+    }
+    Ok(board)
+}
+
+fn square(position: u8) -> Result<(usize, usize), String> {
+    if position >= 90 {
+        return Err(format!("XQF square {} is off the board", position));
+    }
+    let file = position as usize / 10;
+    let rank_from_red = position as usize % 10;
+    Ok((9 - rank_from_red, file))
+}
+
+fn pascal_string(header: &[u8], offset: usize, max_len: usize) -> String {
+    let len = (header[offset] as usize).min(max_len);
+    decode_gbk(&header[offset + 1..offset + 1 + len])
+}
+
+fn decode_gbk(bytes: &[u8]) -> String {
+    let (text, _, _) = GBK.decode(bytes);
+    text.trim_end_matches('\0').trim().to_string()
+}
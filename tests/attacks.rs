@@ -0,0 +1,71 @@
+use chess_engine::attacks::{square_bit, AttackMaps};
+use chess_engine::evaluation::trace_evaluation;
+use chess_engine::{Board, Color, EvalParams, Piece};
+
+// ICCS square, e.g. "e4", as (rank, file)
+fn square(name: &str) -> (usize, usize) {
+    let bytes = name.as_bytes();
+    (9 - (bytes[1] - b'0') as usize, (bytes[0] - b'a') as usize)
+}
+
+#[test]
+fn attack_maps_follow_the_piece_rules() {
+    // Red: cannon e2 behind its own soldier e3, horse c2 with its leg on c3
+    // blocked, soldier g5 across the river. Black: chariot a9, soldier e6.
+    let board = Board::from_fen("r2k5/9/9/4p4/6P2/9/2P1P4/2N1C4/9/4K4 w - - 0 1").unwrap();
+    let attacks = AttackMaps::new(&board);
+
+    assert!(attacks.is_attacked_by(square("e4"), Color::Red, Piece::Cannon));
+    assert!(attacks.is_attacked_by(square("e6"), Color::Red, Piece::Cannon));
+    assert!(!attacks.is_attacked_by(square("e7"), Color::Red, Piece::Cannon));
+    assert!(!attacks.is_attacked_by(square("d2"), Color::Red, Piece::Cannon));
+    assert!(attacks.is_attacked_by(square("a2"), Color::Red, Piece::Cannon));
+    assert!(attacks.is_attacked_by(square("a3"), Color::Red, Piece::Horse));
+    assert!(!attacks.is_attacked_by(square("b4"), Color::Red, Piece::Horse));
+    assert!(attacks.is_attacked_by(square("f5"), Color::Red, Piece::Soldier));
+    assert!(attacks.is_attacked_by(square("g6"), Color::Red, Piece::Soldier));
+    assert!(!attacks.is_attacked_by(square("g4"), Color::Red, Piece::Soldier));
+    assert!(attacks.is_attacked_by(square("a0"), Color::Black, Piece::Chariot));
+    assert!(attacks.is_attacked_by(square("e5"), Color::Black, Piece::Soldier));
+    assert_eq!(attacks.attackers(square("e6"), Color::Red), square_bit(square("e2")));
+    assert_eq!(attacks.attackers(square("a3"), Color::Red), square_bit(square("c2")));
+
+    // a chariot is chased off squares a soldier guards, a soldier is not
+    assert!(attacks.is_attacked_by_cheaper(square("e5"), Color::Black, Piece::Chariot));
+    assert!(!attacks.is_attacked_by_cheaper(square("e5"), Color::Black, Piece::Soldier));
+}
+
+#[test]
+fn attack_maps_agree_with_board_attackers() {
+    // the generals never face each other here, the one capture the maps leave out
+    for fen in [
+        "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR w - - 0 1",
+        "r2k5/9/9/4p4/6P2/9/2P1P4/2N1C4/9/4K4 w - - 0 1",
+        "2bak4/4a4/2n1b1c2/p1R1N3p/2p3p2/6P2/P3c3P/4B1N2/4A4/2BAK4 w - - 0 1",
+    ] {
+        let board = Board::from_fen(fen).unwrap();
+        let attacks = AttackMaps::new(&board);
+        for rank in 0..10 {
+            for file in 0..9 {
+                for color in [Color::Red, Color::Black] {
+                    let expected = board.attackers((rank, file), color).into_iter().fold(0, |mask, from| mask | square_bit(from));
+                    assert_eq!(attacks.attackers((rank, file), color), expected, "{} at {:?} for {:?}", fen, (rank, file), color);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn squares_guarded_by_soldiers_do_not_count_for_horses() {
+    let mobility = |fen: &str| {
+        let trace = trace_evaluation(&Board::from_fen(fen).unwrap(), EvalParams::defaults());
+        let start = trace.rows.iter().position(|row| row.name == "mobility").unwrap();
+        trace.rows[start + 1..].iter().find(|row| row.name == "horse").unwrap().red
+    };
+    // Red's horse on e5 with two crossed black soldiers on the edges, then
+    // on c3 and g3 where they cover d3 and f3
+    let free = mobility("3k5/9/9/9/4N4/9/p7p/9/9/4K4 w - - 0 1");
+    let guarded = mobility("3k5/9/9/9/4N4/9/2p3p2/9/9/4K4 w - - 0 1");
+    assert!(guarded.mg < free.mg, "{:?} vs {:?}", guarded, free);
+}
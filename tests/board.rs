@@ -0,0 +1,69 @@
+use chess_engine::{Board, Color, Move};
+
+fn in_check(fen: &str, color: Color) -> bool {
+    Board::from_fen(fen).unwrap().is_in_check(color)
+}
+
+#[test]
+fn soldiers_give_check_forwards_and_sideways_across_the_river() {
+    assert!(in_check("4k4/4P4/9/9/9/9/9/9/9/3K5 w - - 0 1", Color::Black));
+    assert!(in_check("3Pk4/9/9/9/9/9/9/9/9/3K5 w - - 0 1", Color::Black));
+    // never backwards
+    assert!(!in_check("4P4/4k4/9/9/9/9/9/9/9/3K5 w - - 0 1", Color::Black));
+}
+
+#[test]
+fn cannons_check_over_exactly_one_screen() {
+    assert!(in_check("4k4/9/9/4P4/9/4C4/9/9/9/3K5 w - - 0 1", Color::Black));
+    assert!(!in_check("4k4/9/9/9/9/4C4/9/9/9/3K5 w - - 0 1", Color::Black));
+    assert!(!in_check("4k4/4a4/9/4P4/9/4C4/9/9/9/3K5 w - - 0 1", Color::Black));
+}
+
+#[test]
+fn horses_are_stopped_by_a_blocked_leg() {
+    assert!(in_check("4k4/9/3N5/9/9/9/9/9/9/3K5 w - - 0 1", Color::Black));
+    assert!(!in_check("4k4/3P5/3N5/9/9/9/9/9/9/3K5 w - - 0 1", Color::Black));
+    // the leg is next to the horse, not next to the general
+    assert!(in_check("3ak4/2N6/9/9/9/9/9/9/9/3K5 w - - 0 1", Color::Black));
+    assert!(!in_check("3ak4/2NP5/9/9/9/9/9/9/9/3K5 w - - 0 1", Color::Black));
+}
+
+#[test]
+fn facing_generals_are_both_in_check() {
+    let open = "4k4/9/9/9/9/9/9/9/9/4K4 w - - 0 1";
+    assert!(in_check(open, Color::Red));
+    assert!(in_check(open, Color::Black));
+    assert!(Board::from_fen(open).unwrap().is_flying_general());
+
+    let screened = "4k4/9/9/9/4p4/9/9/9/9/4K4 w - - 0 1";
+    assert!(!in_check(screened, Color::Red));
+    assert!(!in_check(screened, Color::Black));
+}
+
+// Red's soldiers start on rank 3 and Black's on rank 6; an earlier version
+// had the two soldier ranks swapped
+#[test]
+fn initial_position_matches_the_start_fen() {
+    let mut board = Board::new();
+    board.setup_initial_position();
+    assert_eq!(board.to_fen(), "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR w - - 0 1");
+}
+
+// The fullmove number counts full moves, so it goes up after Black's reply
+// (as in chess FEN), not after Red's move
+#[test]
+fn fen_round_trips_through_moves() {
+    let mut board = Board::new();
+    board.setup_initial_position();
+    let expected = [
+        "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C2C4/9/RNBAKABNR b - - 1 1",
+        "rnbakab1r/9/1c4nc1/p1p1p1p1p/9/9/P1P1P1P1P/1C2C4/9/RNBAKABNR w - - 2 2",
+        "rnbakab1r/9/1c4nc1/p1p1p1p1p/9/9/P1P1P1P1P/1C2C1N2/9/RNBAKAB1R b - - 3 2",
+    ];
+    for (mv, fen) in ["h2e2", "h9g7", "h0g2"].iter().zip(expected) {
+        let mv = Move::from_uci(mv).unwrap();
+        assert!(board.make_move(mv.from, mv.to));
+        assert_eq!(board.to_fen(), fen);
+        assert_eq!(Board::from_fen(fen).unwrap().to_fen(), fen);
+    }
+}
@@ -0,0 +1,173 @@
+use std::env;
+use std::fs;
+use std::process::Command;
+use chess_engine::book::{Book, BookBuilder, BookEntry, BuildOptions};
+use chess_engine::{Board, Move};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+fn initial() -> Board {
+    let mut board = Board::new();
+    board.setup_initial_position();
+    board
+}
+
+fn entry(board: &Board, mv: &str, weight: u16) -> BookEntry {
+    BookEntry::new(board, &Move::from_uci(mv).unwrap(), weight)
+}
+
+fn sample_book() -> Book {
+    let start = initial();
+    let mut after = start.clone();
+    after.make_move((7, 7), (7, 4)); // h2e2
+    Book::new(vec![
+        entry(&start, "b2e2", 10),
+        entry(&after, "h9g7", 5),
+        entry(&start, "h2e2", 40),
+        entry(&start, "h2h9", 0),  // never played
+        entry(&start, "a0a5", 50), // illegal here
+    ])
+}
+
+#[test]
+fn round_trips_through_bytes() {
+    let book = sample_book();
+    let reread = Book::from_bytes(&book.to_bytes()).unwrap();
+    assert_eq!(reread.entries(), book.entries());
+    assert!(Book::from_bytes(b"not a book").is_err());
+    assert!(Book::from_bytes(&book.to_bytes()[..25]).is_err());
+
+    // books keyed before canonical keys are refused, not misread
+    let mut old = book.to_bytes();
+    old[6] = b'1';
+    assert!(Book::from_bytes(&old).is_err_and(|e| e.contains("rebuild")));
+}
+
+#[test]
+fn probes_legal_moves_heaviest_first() {
+    let book = sample_book();
+    let moves: Vec<(String, u16)> = book.probe(&initial()).into_iter().map(|(mv, w)| (mv.to_uci(), w)).collect();
+    assert_eq!(moves, [("h2e2".to_string(), 40), ("b2e2".to_string(), 10)]);
+
+    let mut board = initial();
+    board.make_move((9, 0), (8, 0)); // a0a1, out of book
+    assert!(book.probe(&board).is_empty());
+}
+
+#[test]
+fn variety_controls_the_choice() {
+    let book = sample_book();
+    let mut rng = StdRng::seed_from_u64(7);
+    for _ in 0..20 {
+        assert_eq!(book.choose(&initial(), 0, &mut rng), Move::from_uci("h2e2"));
+    }
+    let picks: Vec<Move> = (0..200).filter_map(|_| book.choose(&initial(), 100, &mut rng)).collect();
+    let rare = picks.iter().filter(|&mv| Some(mv) == Move::from_uci("b2e2").as_ref()).count();
+    assert!(rare > 10 && rare < 90, "b2e2 picked {} times", rare);
+}
+
+// Raising weights in the thousands to the 100th power overflowed to infinity
+#[test]
+fn low_variety_handles_large_weights() {
+    let start = initial();
+    let book = Book::new(vec![entry(&start, "h2e2", 60_000), entry(&start, "b2e2", 50_000)]);
+    let mut rng = StdRng::seed_from_u64(3);
+    for _ in 0..50 {
+        assert_eq!(book.choose(&start, 1, &mut rng), Move::from_uci("h2e2"));
+    }
+    let picks: Vec<Move> = (0..50).filter_map(|_| book.choose(&start, 100, &mut rng)).collect();
+    assert!(picks.contains(&Move::from_uci("b2e2").unwrap()));
+}
+
+fn moves(line: &str) -> Vec<Move> {
+    line.split_whitespace().map(|mv| Move::from_uci(mv).unwrap()).collect()
+}
+
+#[test]
+fn builder_filters_and_merges_mirrored_lines() {
+    let options = BuildOptions {
+        max_ply: 3,
+        min_count: 2,
+        min_score: 25,
+    };
+    let mut builder = BookBuilder::new(options);
+    builder.add_game(&initial(), &moves("h2e2 h9g7 h0g2 i9h9"), "1-0").unwrap();
+    builder.add_game(&initial(), &moves("b2e2 b9c7 b0c2"), "0-1").unwrap();
+    builder.add_game(&initial(), &moves("h2e2 h9g7"), "1-0").unwrap();
+    builder.add_game(&initial(), &moves("c3c4 c6c5"), "0-1").unwrap();
+    builder.add_game(&initial(), &moves("c3c4 c6c5"), "0-1").unwrap();
+    assert!(builder.add_game(&initial(), &moves("h2e2 h2h9"), "*").is_err());
+    let book = builder.build();
+
+    // h2e2 and b2e2 are different moves from the symmetric start position;
+    // c3c4 was played twice but lost both times
+    let start: Vec<String> = book.probe(&initial()).iter().map(|(mv, _)| mv.to_uci()).collect();
+    assert_eq!(start, ["h2e2"]);
+
+    // the reply counts games from both mirror images and is found from either
+    let mut after_h = initial();
+    after_h.make_move((7, 7), (7, 4));
+    let mut after_b = initial();
+    after_b.make_move((7, 1), (7, 4));
+    let weight = 2; // one win for Black and two losses, at two points per win
+    assert_eq!(book.probe(&after_h), [(Move::from_uci("h9g7").unwrap(), weight)]);
+    assert_eq!(book.probe(&after_b), [(Move::from_uci("b9c7").unwrap(), weight)]);
+
+    // the fourth ply is beyond the depth limit
+    let mut deep = after_h.clone();
+    deep.make_move((0, 7), (2, 6));
+    deep.make_move((9, 7), (7, 6));
+    assert!(book.probe(&deep).is_empty());
+}
+
+#[test]
+fn rejected_games_leave_the_builder_untouched() {
+    let options = BuildOptions {
+        max_ply: 2,
+        min_count: 1,
+        min_score: 0,
+    };
+    let mut builder = BookBuilder::new(options);
+    builder.add_game(&initial(), &moves("h2e2 h9g7"), "1-0").unwrap();
+    // illegal on the second ply, and past the ply limit on the fourth
+    assert!(builder.add_game(&initial(), &moves("h2e2 a0a9"), "1-0").is_err());
+    assert!(builder.add_game(&initial(), &moves("h2e2 h9g7 h0g2 a0a9"), "1-0").is_err());
+    assert_eq!(builder.games(), 1);
+
+    let book = builder.build();
+    assert_eq!(book.probe(&initial()), [(Move::from_uci("h2e2").unwrap(), 2)]);
+}
+
+// A game with an illegal move in the middle of a PGN file is reported and
+// skipped; the games around it still make it into the book
+#[test]
+fn build_skips_corrupt_pgn_games() {
+    let dir = env::temp_dir();
+    let games = dir.join(format!("xiangqi-book-{}.pgn", std::process::id()));
+    let output = dir.join(format!("xiangqi-book-{}.bin", std::process::id()));
+    fs::write(
+        &games,
+        "[Event \"one\"]\n[Result \"1-0\"]\n\nh2e2 h9g7 1-0\n\n\
+         [Event \"two\"]\n[Result \"0-1\"]\n\nh2e2 a0a9 0-1\n\n\
+         [Event \"three\"]\n[Result \"1-0\"]\n\nh2e2 b9c7 1-0\n",
+    )
+    .unwrap();
+    let run = Command::new(env!("CARGO_BIN_EXE_chess_engine"))
+        .args(["book", "build", "--min-count", "1", "--min-score", "0", "-o"])
+        .arg(&output)
+        .arg(&games)
+        .output()
+        .unwrap();
+    let (stdout, stderr) = (String::from_utf8_lossy(&run.stdout), String::from_utf8_lossy(&run.stderr));
+    assert!(run.status.success(), "{}", stderr);
+    assert!(stderr.contains("Game 2, ply 2: Illegal move: a0a9"), "{}", stderr);
+    assert!(stdout.contains("2 games (1 skipped)"), "{}", stdout);
+
+    let book = Book::load(output.to_str().unwrap()).unwrap();
+    let mut after = initial();
+    after.make_move((7, 7), (7, 4)); // h2e2
+    let replies: Vec<String> = book.probe(&after).iter().map(|(mv, _)| mv.to_uci()).collect();
+    assert_eq!(replies.len(), 2, "{:?}", replies);
+    fs::remove_file(games).unwrap();
+    fs::remove_file(output).unwrap();
+}
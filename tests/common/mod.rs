@@ -0,0 +1,34 @@
+// Helpers shared by the integration tests
+use chess_engine::{generate_legal_moves, Board, Move};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+const GAME_PLIES: usize = 120; // random games restart from the initial position after this
+
+// `count` positions from random games, every position of each game in turn
+pub fn random_positions(count: usize, seed: u64) -> Vec<Board> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut positions = Vec::new();
+    let mut board = Board::new();
+    board.setup_initial_position();
+    let mut plies = 0;
+    while positions.len() < count {
+        let moves: Vec<Move> = generate_legal_moves(&board)
+            .into_iter()
+            .filter(|mv| board.clone().make_move(mv.from, mv.to))
+            .collect();
+        match moves.choose(&mut rng) {
+            Some(mv) if plies < GAME_PLIES => {
+                board.make_move(mv.from, mv.to);
+                positions.push(board.clone());
+                plies += 1;
+            }
+            _ => {
+                board.setup_initial_position();
+                plies = 0;
+            }
+        }
+    }
+    positions
+}
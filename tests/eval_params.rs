@@ -0,0 +1,75 @@
+use std::env;
+use std::fs;
+use chess_engine::evaluation::{evaluate_with, s};
+use chess_engine::{evaluate_position, Board, EvalParams};
+
+fn temp_file(name: &str, contents: &str) -> String {
+    let path = env::temp_dir().join(format!("xiangqi-eval-{}-{}", std::process::id(), name));
+    fs::write(&path, contents).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+fn initial() -> Board {
+    let mut board = Board::new();
+    board.setup_initial_position();
+    board
+}
+
+#[test]
+fn defaults_drive_the_evaluation() {
+    let params = EvalParams::default();
+    assert_eq!(params.chariot.value, s(600, 620));
+    assert_eq!(params.soldier.psq_mg[0][4], 32);
+    let board = Board::from_fen("r1bakabr1/9/1c2n2c1/p1p1p1p1p/9/9/P1P1P1P1P/1C2N2N1/9/R1BAKAB1R b - - 0 1").unwrap();
+    assert_eq!(evaluate_with(&board, &params), evaluate_position(&board));
+}
+
+#[test]
+fn files_override_only_what_they_list() {
+    let toml = temp_file("partial.toml", "cannon_platform = [7, 3]\n[chariot]\nvalue = [650, 700]\n");
+    let params = EvalParams::load(&toml).unwrap();
+    assert_eq!(params.cannon_platform, s(7, 3));
+    assert_eq!(params.chariot.value, s(650, 700));
+    assert_eq!(params.chariot.psq_mg, EvalParams::default().chariot.psq_mg);
+    assert_eq!(params.horse, EvalParams::default().horse);
+
+    let json = temp_file("partial.json", r#"{ "horse": { "value": [300, 300] } }"#);
+    assert_eq!(EvalParams::load(&json).unwrap().horse.value, s(300, 300));
+
+    let typo = temp_file("typo.toml", "cannon_platfrom = [7, 3]\n");
+    assert!(EvalParams::load(&typo).is_err());
+    for path in [toml, json, typo] {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn saved_files_load_back_unchanged() {
+    let mut params = EvalParams::default();
+    params.set("cannon.value", &[290, 240]).unwrap();
+    params.set("horse.psq_eg.2.4", &[25]).unwrap();
+    params.set("protector", &[12]).unwrap();
+    assert_eq!(params.horse.psq_eg[2][4], 25);
+    assert_eq!(params.protector, s(12, 12));
+    assert!(params.set("cannon.valu", &[1]).is_err());
+    assert!(params.set("horse.psq_eg.2", &[1]).is_err());
+
+    for name in ["saved.toml", "saved.json"] {
+        let path = temp_file(name, "");
+        params.save(&path).unwrap();
+        assert_eq!(EvalParams::load(&path).unwrap(), params);
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn changed_weights_change_the_score() {
+    // Black, to move, has the extra cannon and Red the extra horse
+    let board = Board::from_fen("r1bakabr1/9/1c2n2c1/p1p1p1p1p/9/9/P1P1P1P1P/1C2N2N1/9/R1BAKAB1R b - - 0 1").unwrap();
+    let mut params = EvalParams::default();
+    let before = evaluate_with(&board, &params);
+    params.set("cannon.value", &[params.cannon.value.mg + 50, params.cannon.value.eg + 50]).unwrap();
+    let gain = evaluate_with(&board, &params) - before;
+    assert!((49..=51).contains(&gain), "{}", gain);
+    assert_eq!(evaluate_position(&initial()), evaluate_with(&initial(), EvalParams::defaults()));
+}
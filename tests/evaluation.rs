@@ -0,0 +1,109 @@
+use chess_engine::evaluation::{Score, MAX_PHASE};
+use chess_engine::{evaluate_position, evaluate_terms, game_phase, trace_evaluation, Board, EvalParams};
+
+fn eval(fen: &str) -> i32 {
+    evaluate_position(&Board::from_fen(fen).unwrap())
+}
+
+#[test]
+fn phase_follows_heavy_material() {
+    let mut start = Board::new();
+    start.setup_initial_position();
+    assert_eq!(game_phase(&start), MAX_PHASE);
+    let bare = Board::from_fen("3ak4/9/9/9/9/9/9/9/9/4K4 w - - 0 1").unwrap();
+    assert_eq!(game_phase(&bare), 0);
+    let one_chariot_each = Board::from_fen("3ak4/9/9/9/9/r8/9/9/8R/4K4 w - - 0 1").unwrap();
+    assert!(game_phase(&one_chariot_each) > 0 && game_phase(&one_chariot_each) < MAX_PHASE / 2);
+}
+
+#[test]
+fn horse_outgrows_cannon_in_the_endgame() {
+    // Red's horse against Black's cannon on matching squares, everything else equal
+    let endgame = eval("3akab2/9/4b2c1/9/9/9/9/4B2N1/9/3AKAB2 w - - 0 1");
+    assert!(endgame > 0, "horse vs cannon endgame scored {}", endgame);
+
+    // with the rest of the army still on the board the cannon is preferred
+    let middlegame = eval("r1bakabr1/9/1c2n2c1/p1p1p1p1p/9/9/P1P1P1P1P/1C2N2N1/9/R1BAKAB1R w - - 0 1");
+    let swapped = eval("r1bakabr1/9/1c2n2n1/p1p1p1p1p/9/9/P1P1P1P1P/1C2N2C1/9/R1BAKAB1R w - - 0 1");
+    assert!(swapped > middlegame, "cannon {} vs horse {}", swapped, middlegame);
+}
+
+#[test]
+fn trace_adds_up_to_the_evaluation() {
+    let board = Board::from_fen("r1bakab1r/9/1cn3nc1/p1p1p3p/6p2/2P6/P3P1P1P/1C2C1N2/9/RNBAKAB1R b - - 0 1").unwrap();
+    let trace = trace_evaluation(&board, EvalParams::defaults());
+    assert_eq!(trace.score, evaluate_position(&board));
+    assert_eq!(trace.red_score(), -trace.score);
+    assert!(!trace.flying_general);
+
+    // the main rows are the terms, before blending
+    let terms = evaluate_terms(&board);
+    let main: Vec<_> = trace.rows.iter().filter(|row| !row.part && row.name != "flying general").collect();
+    assert_eq!(main.len(), terms.len());
+    for (row, term) in main.iter().zip(&terms) {
+        assert_eq!(row.name, term.name);
+        assert_eq!((row.red.taper(trace.phase), row.black.taper(trace.phase)), (term.red, term.black));
+    }
+
+    // the piece rows split the piece squares
+    let squares = trace.rows.iter().position(|row| row.name == "piece squares").unwrap();
+    let parts = trace.rows[squares + 1..].iter().take_while(|row| row.part);
+    let (red, black) = parts.fold((Score::default(), Score::default()), |(red, black), row| (red + row.red, black + row.black));
+    assert_eq!((red, black), (trace.rows[squares].red, trace.rows[squares].black));
+    assert!(trace.to_string().ends_with(&format!("{:+} for Red, {:+} for the side to move (Black)", -trace.score, trace.score)));
+}
+
+#[test]
+fn trace_shows_the_flying_general() {
+    let board = Board::from_fen("4k4/9/9/9/9/9/9/9/9/4K4 b - - 0 1").unwrap();
+    let trace = trace_evaluation(&board, EvalParams::defaults());
+    assert!(trace.flying_general);
+    assert_eq!(trace.score, evaluate_position(&board));
+    let row = trace.rows.iter().find(|row| row.name == "flying general").unwrap();
+    assert_eq!((row.red, row.black.mg), (Score::default(), trace.score));
+}
+
+#[test]
+fn king_safety_sees_cannons_and_chariots() {
+    // Black's king safety parts, by name, as middlegame values
+    let parts = |fen: &str| {
+        let trace = trace_evaluation(&Board::from_fen(fen).unwrap(), EvalParams::defaults());
+        let start = trace.rows.iter().position(|row| row.name == "king safety").unwrap();
+        trace.rows[start + 1..]
+            .iter()
+            .take_while(|row| row.part)
+            .map(|row| (row.name.clone(), row.black.mg))
+            .collect::<std::collections::HashMap<_, _>>()
+    };
+
+    // a cannon facing the general with nothing in between, and a chariot
+    // holding d9 beside it: the iron bolt
+    let bolt = parts("3akab2/9/9/9/9/9/4C4/9/9/3RK4 b - - 0 1");
+    assert!(bolt["empty-headed cannons"] < 0 && bolt["iron bolts"] < 0);
+    assert_eq!(bolt["central cannons"], 0);
+
+    // an elephant on e7 turns it into an ordinary central cannon
+    let screened = parts("3akab2/9/4b4/9/9/9/4C4/9/9/3RK4 b - - 0 1");
+    assert!(screened["central cannons"] < 0);
+    assert_eq!((screened["empty-headed cannons"], screened["iron bolts"]), (0, 0));
+
+    // a second screen on e5 leaves the cannon nothing to attack
+    let two_screens = parts("3akab2/9/4b4/9/4p4/9/4C4/9/9/3RK4 b - - 0 1");
+    assert_eq!((two_screens["central cannons"], two_screens["empty-headed cannons"]), (0, 0));
+
+    // without an advisor the enemy chariot counts more
+    let advisor_lost = parts("4kab2/9/4b4/9/9/9/4C4/9/9/3RK4 b - - 0 1");
+    assert!(advisor_lost["missing advisors"] < 0 && advisor_lost["open palace files"] < screened["open palace files"]);
+}
+
+#[test]
+fn palace_horses_include_those_a_move_away() {
+    let palace_horses = |fen: &str| {
+        let trace = trace_evaluation(&Board::from_fen(fen).unwrap(), EvalParams::defaults());
+        trace.rows.iter().find(|row| row.name == "palace horses").unwrap().black.mg
+    };
+    // from e4 the horse hits nothing in the palace, but d6 attacks e8; from
+    // a0 it is further away
+    assert!(palace_horses("3akab2/9/4b4/9/9/4N4/9/9/9/4K4 b - - 0 1") < 0);
+    assert_eq!(palace_horses("3akab2/9/4b4/9/9/9/9/9/9/N3K4 b - - 0 1"), 0);
+}
@@ -0,0 +1,148 @@
+use chess_engine::notation::{format_chinese, format_wxf, parse_chinese, parse_move, parse_wxf};
+use chess_engine::{generate_legal_moves, Board, Move};
+
+fn board(fen: &str) -> Board {
+    Board::from_fen(fen).expect("test FEN is valid")
+}
+
+fn start() -> Board {
+    let mut board = Board::new();
+    board.setup_initial_position();
+    board
+}
+
+fn mv(uci: &str) -> Move {
+    Move::from_uci(uci).expect("test move is valid ICCS")
+}
+
+// Formats `uci` in both notations and checks each parses back to the same move
+fn check(board: &Board, uci: &str, wxf: &str, chinese: &str) {
+    let mv = mv(uci);
+    assert_eq!(format_wxf(board, &mv).unwrap(), wxf, "WXF for {}", uci);
+    assert_eq!(format_chinese(board, &mv).unwrap(), chinese, "Chinese for {}", uci);
+    assert_eq!(parse_wxf(board, wxf), Ok(mv.clone()), "parsing {}", wxf);
+    assert_eq!(parse_chinese(board, chinese), Ok(mv), "parsing {}", chinese);
+}
+
+#[test]
+fn opening_moves() {
+    let mut board = start();
+    check(&board, "h2e2", "C2=5", "炮二平五");
+    check(&board, "h0g2", "H2+3", "马二进三");
+    check(&board, "c3c4", "P7+1", "兵七进一");
+    check(&board, "g0e2", "E3+5", "相三进五");
+    check(&board, "f0e1", "A4+5", "仕四进五");
+    check(&board, "e0e1", "K5+1", "帅五进一");
+
+    assert!(board.make_move((7, 7), (7, 4)));
+    check(&board, "h9g7", "H8+7", "马8进7");
+    check(&board, "b7e7", "C2=5", "炮2平5");
+    check(&board, "a9a8", "R1+1", "车1进1");
+    check(&board, "g6g5", "P7+1", "卒7进1");
+}
+
+#[test]
+fn retreats_and_traverses() {
+    let board = board("3k5/9/9/9/2R6/9/9/4C4/9/4K4 w - - 0 1");
+    check(&board, "c5c2", "R7-3", "车七退三");
+    check(&board, "c5h5", "R7=2", "车七平二");
+    check(&board, "e2e6", "C5+4", "炮五进四");
+}
+
+#[test]
+fn doubled_chariots_use_front_and_rear() {
+    let board = board("3k5/9/9/9/9/9/4R4/4R4/9/4K4 w - - 0 1");
+    check(&board, "e3e4", "R++1", "前车进一");
+    check(&board, "e2d2", "R-=6", "后车平六");
+}
+
+#[test]
+fn doubled_black_horses_count_front_from_black_side() {
+    // Black's front horse is the one nearer Red, on the higher rank index
+    let board = board("4k4/9/1n7/9/1n7/9/9/9/9/3K5 b - - 0 1");
+    check(&board, "b5c3", "H++3", "前马进3");
+    check(&board, "b5a3", "H++1", "前马进1");
+    check(&board, "b7d8", "H--4", "后马退4");
+    check(&board, "b7c5", "H-+3", "后马进3");
+}
+
+#[test]
+fn three_soldiers_on_a_file() {
+    let board = board("3k5/9/2P6/2P6/2P6/9/9/9/9/4K4 w - - 0 1");
+    check(&board, "c7c8", "P++1", "前兵进一");
+    check(&board, "c6b6", "2P=8", "中兵平八");
+    check(&board, "c5d5", "P-=6", "后兵平六");
+}
+
+#[test]
+fn four_soldiers_on_a_file_are_numbered() {
+    let board = board("3k5/2P6/2P6/2P6/2P6/9/9/9/9/4K4 w - - 0 1");
+    check(&board, "c8c9", "1P+1", "一兵进一");
+    check(&board, "c7b7", "2P=8", "二兵平八");
+    check(&board, "c6d6", "3P=6", "三兵平六");
+    check(&board, "c5b5", "4P=8", "四兵平八");
+}
+
+#[test]
+fn soldiers_stacked_on_two_files_name_the_file() {
+    let board = board("3k5/9/9/2P3P2/2P3P2/9/9/9/9/4K4 w - - 0 1");
+    check(&board, "c6c7", "+7+1", "前七进一");
+    check(&board, "g5f5", "-3=4", "后三平四");
+}
+
+#[test]
+fn doubled_advisors_keep_the_file_number() {
+    let board = board("3k5/9/9/9/9/9/9/3A5/9/3A1K3 w - - 0 1");
+    check(&board, "d0e1", "A6+5", "仕六进五");
+    check(&board, "d2e1", "A6-5", "仕六退五");
+}
+
+#[test]
+fn every_legal_move_round_trips() {
+    let fens = [
+        "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR w - - 0 1",
+        "r1b1kab2/9/1c4n2/p1c3p1p/4p4/2P6/P3P1PrP/2N1B4/1C2A4/2R1KABNR w - - 17 15",
+        "1rbakab1r/9/7cn/p1p1p1p1p/9/9/P1P1P1PCP/1c2B4/3N5/R2AKABNR b - - 5 5",
+        "4kab2/4a4/4b4/pP2p3p/2rc5/6B2/P2c4P/3A2N2/1C7/4KABR1 w - - 11 23",
+        "2bakab2/9/9/4P4/4P4/4P4/4P4/9/9/4K4 w - - 0 1",
+        "4k4/4a4/4ba3/2p1pp3/2p1p4/9/9/9/9/3K5 b - - 0 1",
+    ];
+    for fen in fens {
+        let board = board(fen);
+        for mv in generate_legal_moves(&board) {
+            if !board.clone().make_move(mv.from, mv.to) {
+                continue;
+            }
+            let wxf = format_wxf(&board, &mv).unwrap();
+            let chinese = format_chinese(&board, &mv).unwrap();
+            assert_eq!(parse_wxf(&board, &wxf), Ok(mv.clone()), "{} in {}", wxf, fen);
+            assert_eq!(parse_chinese(&board, &chinese), Ok(mv.clone()), "{} in {}", chinese, fen);
+        }
+    }
+}
+
+#[test]
+fn parse_move_accepts_any_notation() {
+    let board = start();
+    for text in ["h2e2", "H2-E2", "C2=5", "c2.5", "炮二平五", "砲二平五"] {
+        assert_eq!(parse_move(&board, text), Ok(mv("h2e2")), "{}", text);
+    }
+    assert_eq!(parse_move(&board, "N2+3"), Ok(mv("h0g2")));
+    assert_eq!(parse_move(&board, "B3+5"), Ok(mv("g0e2")));
+
+    let mut board = start();
+    assert!(board.make_move((7, 7), (7, 4)));
+    for text in ["马8进7", "馬8進7", "马８进７", "H8+7"] {
+        assert_eq!(parse_move(&board, text), Ok(mv("h9g7")), "{}", text);
+    }
+}
+
+#[test]
+fn parse_move_rejects_bad_input() {
+    let board = start();
+    assert!(parse_move(&board, "h2h8").is_err()); // cannot pass the black cannon
+    assert!(parse_move(&board, "C2+5").is_err());
+    assert!(parse_move(&board, "X2=5").is_err());
+    assert!(parse_move(&board, "炮二平").is_err());
+    assert!(parse_move(&board, "前车进一").is_err()); // no doubled chariots
+}
@@ -0,0 +1,89 @@
+use chess_engine::game::ROOT;
+use chess_engine::pgn::{parse_pgn, parse_pgn_games, write_pgn};
+use chess_engine::Move;
+
+const TWO_GAMES: &str = r#"[Game "Chinese Chess"]
+[Variant "xiangqi"]
+[Red "Alpha"]
+[Black "Beta \"B\""]
+[Format "WXF"]
+[Result "1-0"]
+
+{Central cannon opening} 1. C2=5 H8+7 (1... C8=5 {same direction cannons}) 2. H2+3
+R9=8 ; a line comment
+3. R1=2 $1 P7+1! 1-0
+
+[Game "Chinese Chess"]
+[FEN "4k4/9/9/9/9/9/9/9/4R4/3K5 b - - 0 1"]
+
+1... e9f9 2. e1f1 *
+"#;
+
+fn iccs(moves: &[Move]) -> Vec<String> {
+    moves.iter().map(|mv| mv.to_uci()).collect()
+}
+
+#[test]
+fn parses_headers_moves_comments_and_variations() {
+    let games = parse_pgn(TWO_GAMES).unwrap();
+    assert_eq!(games.len(), 2);
+
+    let game = &games[0];
+    assert_eq!(game.header("Black"), Some("Beta \"B\""));
+    assert_eq!(game.header("Result"), Some("1-0"));
+    assert_eq!(game.nodes[ROOT].comment.as_deref(), Some("Central cannon opening"));
+    assert_eq!(
+        iccs(&game.mainline_moves()),
+        ["h2e2", "h9g7", "h0g2", "i9h9", "i0h0", "g6g5"]
+    );
+
+    let first = game.nodes[ROOT].children[0];
+    let replies = &game.nodes[first].children;
+    assert_eq!(replies.len(), 2);
+    let variation = replies[1];
+    assert_eq!(game.nodes[variation].mv, Move::from_uci("h7e7"));
+    assert_eq!(game.nodes[variation].comment.as_deref(), Some("same direction cannons"));
+
+    let second = &games[1];
+    assert!(!second.start.red_to_move);
+    assert_eq!(iccs(&second.mainline_moves()), ["e9f9", "e1f1"]);
+    assert_eq!(second.header("Result"), Some("*"));
+}
+
+#[test]
+fn written_games_read_back_identically() {
+    for game in parse_pgn(TWO_GAMES).unwrap() {
+        let text = write_pgn(&game);
+        let reread = parse_pgn(&text).unwrap();
+        assert_eq!(reread.len(), 1, "{}", text);
+        let reread = &reread[0];
+        assert_eq!(reread.headers, game.headers);
+        assert_eq!(reread.start.to_fen(), game.start.to_fen());
+        assert_eq!(reread.nodes.len(), game.nodes.len());
+        for (a, b) in reread.nodes.iter().zip(&game.nodes) {
+            assert_eq!(a.mv, b.mv);
+            assert_eq!(a.comment, b.comment);
+            assert_eq!(a.children, b.children);
+        }
+    }
+}
+
+#[test]
+fn rejects_illegal_moves_with_their_position() {
+    let error = parse_pgn("[Event \"x\"]\n\n1. h2e2 h9g7 2. h2h9 *\n").err().unwrap();
+    assert!(error.contains("Game 1, ply 3"), "{}", error);
+    assert!(parse_pgn("1. h2e2 (1. b2e2\n").is_err());
+}
+
+#[test]
+fn damaged_games_do_not_hide_the_others() {
+    let text = "[Event \"one\"]\n\nh2e2 h9g7 1-0\n\n\
+                [Event \"two\"]\n\nh2e2 a0a9 0-1\n\n\
+                [Event \"three\"]\n\nh2e2 b9c7 *\n";
+    assert!(parse_pgn(text).is_err());
+    let games = parse_pgn_games(text);
+    assert_eq!(games.len(), 3);
+    assert_eq!(games[0].as_ref().unwrap().header("Event"), Some("one"));
+    assert!(games[1].as_ref().is_err_and(|e| e.starts_with("Game 2, ply 2")));
+    assert_eq!(games[2].as_ref().unwrap().header("Event"), Some("three"));
+}
@@ -0,0 +1,32 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+// Runs `chess_engine play` with the given arguments and terminal input
+fn play(args: &[&str], input: &str) -> Output {
+    let mut engine = Command::new(env!("CARGO_BIN_EXE_chess_engine"))
+        .arg("play")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    engine.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    engine.wait_with_output().unwrap()
+}
+
+#[test]
+fn zero_movetime_is_rejected() {
+    let output = play(&["black", "0"], "quit\n");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("at least 1 ms"));
+}
+
+#[test]
+fn engine_moves_once_and_waits_for_the_human() {
+    let output = play(&["black", "50"], "time 0\nquit\n");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("Engine plays").count(), 1, "{}", stdout);
+    assert!(stdout.contains("Usage: time <milliseconds>, at least 1"), "{}", stdout);
+}
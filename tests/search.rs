@@ -0,0 +1,142 @@
+use chess_engine::search::{mate_in, see};
+use chess_engine::{generate_legal_moves, has_legal_move, Board, IterationReport, Move, ScoreBound, SearchLimits, SearchObserver, Searcher};
+
+fn exchange(fen: &str, mv: &str) -> i32 {
+    see(&Board::from_fen(fen).unwrap(), &Move::from_uci(mv).unwrap())
+}
+
+fn search_depth(fen: &str, depth: i32) -> chess_engine::SearchResult {
+    let limits = SearchLimits { depth: Some(depth), ..Default::default() };
+    Searcher::new(1, 1).search(&Board::from_fen(fen).unwrap(), &limits, &mut ())
+}
+
+#[test]
+fn see_counts_the_recapture() {
+    // chariot takes a horse, alone and then defended by the black chariot
+    assert_eq!(exchange("3k5/9/9/9/2n6/9/9/9/2R6/5K3 w - - 0 1", "c1c5"), 270);
+    assert_eq!(exchange("3k5/2r6/9/9/2n6/9/9/9/2R6/5K3 w - - 0 1", "c1c5"), 270 - 600);
+}
+
+#[test]
+fn see_finds_cannons_behind_the_capturing_piece() {
+    // the chariot leaving c3 lines the cannon on c0 up with c5 over the horse
+    // on c2, so the black chariot's recapture is answered
+    assert_eq!(exchange("3k5/2r6/9/9/2n6/9/2R6/2N6/9/2C2K3 w - - 0 1", "c3c5"), 270);
+}
+
+#[test]
+fn see_values_crossed_soldiers_higher() {
+    assert_eq!(exchange("3k5/9/9/9/9/9/2p6/9/2R6/5K3 w - - 0 1", "c1c3"), 60);
+    assert_eq!(exchange("3k5/9/9/2p6/9/9/9/9/2R6/5K3 w - - 0 1", "c1c6"), 30);
+}
+
+#[test]
+fn finds_mate_in_one() {
+    // a0a9 mates; b8b9 only checks and the general steps to d8
+    let result = search_depth("3k5/1R7/9/9/9/9/9/9/9/R4K3 w - - 0 1", 3);
+    assert_eq!(result.best_move, Move::from_uci("a0a9"));
+    assert_eq!(mate_in(result.score), Some(1));
+}
+
+fn legal_replies(board: &Board) -> Vec<Board> {
+    generate_legal_moves(board)
+        .into_iter()
+        .filter_map(|mv| {
+            let mut next = board.clone();
+            next.make_move(mv.from, mv.to).then_some(next)
+        })
+        .collect()
+}
+
+#[test]
+fn finds_mate_in_two() {
+    // e.g. b1b8, taking rank 8 away, then a0a9; there is no mate in one
+    let board = Board::from_fen("3k5/9/9/9/9/9/9/9/1R7/R4K3 w - - 0 1").unwrap();
+    let result = search_depth(&board.to_fen(), 5);
+    assert_eq!(mate_in(result.score), Some(2));
+
+    // every reply to the chosen move runs into a mate in one
+    let mv = result.best_move.unwrap();
+    let mut after = board.clone();
+    assert!(after.make_move(mv.from, mv.to));
+    let replies = legal_replies(&after);
+    assert!(!replies.is_empty());
+    for reply in replies {
+        assert!(legal_replies(&reply).iter().any(|mated| !has_legal_move(mated)), "{}", reply.to_fen());
+    }
+}
+
+// At depth 1 the capture is only refuted inside the quiescence search, which
+// must not stand pat on the chariot left hanging on a5
+#[test]
+fn quiescence_sees_the_recapture() {
+    let free = search_depth("3k5/9/9/9/n8/9/9/9/9/R4K3 w - - 0 1", 1);
+    assert_eq!(free.best_move, Move::from_uci("a0a5"));
+
+    let defended = search_depth("r2k5/9/9/9/n8/9/9/9/9/R4K3 w - - 0 1", 1);
+    assert_ne!(defended.best_move, Move::from_uci("a0a5"));
+    assert!(defended.score < 100, "{}", defended.score);
+}
+
+// Same answer and node count on every run, whatever Threads is set to
+#[test]
+fn deterministic_searches_repeat_exactly() {
+    let mut board = Board::new();
+    board.setup_initial_position();
+    let limits = SearchLimits { nodes: Some(5_000), ..Default::default() };
+    let mut outcomes = Vec::new();
+    for threads in [1, 3] {
+        let mut searcher = Searcher::new(1, threads);
+        searcher.set_deterministic(true);
+        for _ in 0..2 {
+            let result = searcher.search(&board, &limits, &mut ());
+            outcomes.push((result.best_move, result.score, result.nodes));
+        }
+    }
+    assert!(outcomes.windows(2).all(|pair| pair[0] == pair[1]), "{:?}", outcomes);
+}
+
+// Limits too small for even one iteration still leave a move to play
+#[test]
+fn tiny_limits_still_return_a_legal_move() {
+    let mut board = Board::new();
+    board.setup_initial_position();
+    let tiny = [
+        SearchLimits { nodes: Some(1), ..Default::default() },
+        SearchLimits { time_limit: Some(0), ..Default::default() },
+    ];
+    for limits in tiny {
+        for threads in [1, 2] {
+            let result = Searcher::new(1, threads).search(&board, &limits, &mut ());
+            let mv = result.best_move.expect("a legal move");
+            assert!(generate_legal_moves(&board).contains(&mv) && board.clone().make_move(mv.from, mv.to));
+        }
+    }
+}
+
+#[derive(Default)]
+struct LastReport(Option<(i32, ScoreBound)>);
+
+impl SearchObserver for LastReport {
+    fn on_iteration(&mut self, report: &IterationReport) {
+        self.0 = Some((report.score, report.bound));
+    }
+}
+
+// A node budget that runs out while an aspiration window is re-searched
+// leaves the failed search's score as a bound, as the last report said
+#[test]
+fn result_bound_follows_the_last_iteration() {
+    let board = Board::from_fen("2bak1b2/9/3r1a2n/pc1Cn1pr1/4p2C1/2P1P3p/P5N1P/B3B4/1R1KA4/RN1A5 w - - 5 42").unwrap();
+    let mut bounds = Vec::new();
+    for nodes in [3_000, 6_000, 10_000] {
+        let mut searcher = Searcher::new(1, 1);
+        searcher.set_deterministic(true);
+        let mut last = LastReport::default();
+        let limits = SearchLimits { nodes: Some(nodes), ..Default::default() };
+        let result = searcher.search(&board, &limits, &mut last);
+        assert_eq!(last.0, Some((result.score, result.bound)), "nodes {}", nodes);
+        bounds.push(result.bound);
+    }
+    assert!(bounds.contains(&ScoreBound::Lower), "{:?}", bounds);
+}
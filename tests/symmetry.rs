@@ -0,0 +1,103 @@
+mod common;
+
+use chess_engine::book::canonical_key;
+use std::collections::BTreeMap;
+use chess_engine::{evaluate_position, evaluate_terms, generate_legal_moves, Board, Move};
+use common::random_positions;
+
+fn initial() -> Board {
+    let mut board = Board::new();
+    board.setup_initial_position();
+    board
+}
+
+// The board's moves after `transform`, as sorted ICCS strings
+fn sorted_moves(board: &Board, transform: fn(&Move) -> Move) -> Vec<String> {
+    let mut moves: Vec<String> = generate_legal_moves(board).iter().map(|mv| transform(mv).to_uci()).collect();
+    moves.sort();
+    moves
+}
+
+#[test]
+fn transforms_are_involutions() {
+    let start = initial();
+    assert_eq!(start.mirror_files().to_fen(), start.to_fen());
+    let flipped = start.flip_colors();
+    assert!(!flipped.red_to_move);
+    assert_eq!(flipped.flip_colors().to_fen(), start.to_fen());
+
+    for board in random_positions(600, 1) {
+        assert_eq!(board.mirror_files().mirror_files().to_fen(), board.to_fen());
+        assert_eq!(board.flip_colors().flip_colors().to_fen(), board.to_fen());
+    }
+    let mv = Move::from_uci("h2e2").unwrap();
+    assert_eq!(mv.mirror_files().to_uci(), "b2e2");
+    assert_eq!(mv.flip_colors().to_uci(), "h7e7");
+}
+
+#[test]
+fn moves_follow_the_transforms() {
+    for board in random_positions(1200, 2) {
+        let fen = board.to_fen();
+        assert_eq!(sorted_moves(&board.mirror_files(), Move::clone), sorted_moves(&board, Move::mirror_files), "{}", fen);
+        assert_eq!(sorted_moves(&board.flip_colors(), Move::clone), sorted_moves(&board, Move::flip_colors), "{}", fen);
+    }
+}
+
+#[test]
+fn mirror_images_share_a_book_key() {
+    for board in random_positions(600, 3) {
+        let (key, mirrored) = canonical_key(&board);
+        let (mirror_key, mirror_mirrored) = canonical_key(&board.mirror_files());
+        assert_eq!(key, mirror_key);
+        if board.mirror_files().to_fen() != board.to_fen() {
+            assert_ne!(mirrored, mirror_mirrored);
+        }
+    }
+}
+
+// Every evaluation term must give each side the same score after the colors
+// are exchanged (with the sides swapped) or the files mirrored, and must not
+// depend on whose turn it is. Collects the terms that break this, with how
+// often and one position where they do.
+#[test]
+fn evaluation_is_symmetric() {
+    let mut offenders: BTreeMap<String, (usize, String)> = BTreeMap::new();
+    let mut note = |what: String, board: &Board| {
+        offenders.entry(what).or_insert((0, board.to_fen())).0 += 1;
+    };
+
+    let positions = random_positions(4800, 4);
+    assert!(positions.len() > 2000);
+    for board in &positions {
+        let (flipped, mirrored) = (board.flip_colors(), board.mirror_files());
+        let mut passed = board.clone();
+        passed.red_to_move = !board.red_to_move;
+        let terms = evaluate_terms(board);
+        let others = evaluate_terms(&flipped).into_iter().zip(evaluate_terms(&mirrored)).zip(evaluate_terms(&passed));
+        for (term, ((flip, mirror), pass)) in terms.iter().zip(others) {
+            if (term.red, term.black) != (flip.black, flip.red) {
+                note(format!("color flip: {}", term.name), board);
+            }
+            if (term.red, term.black) != (mirror.red, mirror.black) {
+                note(format!("mirror: {}", term.name), board);
+            }
+            if (term.red, term.black) != (pass.red, pass.black) {
+                note(format!("side to move: {}", term.name), board);
+            }
+        }
+        let score = evaluate_position(board);
+        if score != evaluate_position(&flipped) {
+            note("color flip: total".to_string(), board);
+        }
+        if score != evaluate_position(&mirrored) {
+            note("mirror: total".to_string(), board);
+        }
+    }
+
+    let report: Vec<String> = offenders
+        .iter()
+        .map(|(what, (count, fen))| format!("{} differs in {} positions, e.g. {}", what, count, fen))
+        .collect();
+    assert!(report.is_empty(), "asymmetric evaluation:\n{}", report.join("\n"));
+}
@@ -0,0 +1,26 @@
+use chess_engine::tt::{NodeType, TTEntry, TranspositionTable};
+use chess_engine::Move;
+
+fn entry(depth: i32, score: i32, node_type: NodeType, best_move: Option<&str>) -> TTEntry {
+    TTEntry { depth, score, node_type, best_move: best_move.and_then(Move::from_uci) }
+}
+
+#[test]
+fn entries_round_trip_through_the_table() {
+    let tt = TranspositionTable::new(1);
+    let entries = [
+        (0x1234_5678_9abc_def0, entry(7, 123, NodeType::Exact, Some("h2e2"))),
+        (0x0fed_cba9_8765_4321, entry(0, -48_990, NodeType::UpperBound, None)),
+        (0x5555_aaaa_5555_aaaa, entry(-1, 0, NodeType::LowerBound, Some("a9a0"))),
+    ];
+    for (hash, entry) in &entries {
+        tt.store(*hash, entry.clone());
+    }
+    for (hash, entry) in &entries {
+        assert_eq!(tt.probe(*hash).as_ref(), Some(entry));
+    }
+    assert!(tt.probe(0x1234_5678_9abc_def1).is_none());
+
+    tt.clear();
+    assert!(entries.iter().all(|(hash, _)| tt.probe(*hash).is_none()));
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use chess_engine::evaluation::{coefficients, MAX_PHASE};
+use chess_engine::search::quiet_position;
+use chess_engine::tuner::{parse_labelled_position, Tuner, TuningSet};
+use chess_engine::{evaluate_position, Board, EvalParams};
+use common::random_positions;
+
+#[test]
+fn labelled_positions_are_read() {
+    let fen = "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR w - - 0 1";
+    for (line, expected) in [
+        (format!("{} 1-0", fen), 1.0),
+        (format!("{} [0.5]", fen), 0.5),
+        (format!("{} c9 \"0-1\";", fen), 0.0),
+    ] {
+        let (board, result) = parse_labelled_position(&line).unwrap();
+        assert_eq!(board.to_fen(), fen);
+        assert_eq!(result, expected);
+    }
+    assert!(parse_labelled_position(fen).is_err());
+    assert!(parse_labelled_position(&format!("{} [2]", fen)).is_err());
+}
+
+#[test]
+fn weight_vectors_round_trip() {
+    let params = EvalParams::default();
+    let values = params.to_vector();
+    assert_eq!(EvalParams::from_vector(&values).unwrap(), params);
+    assert!(EvalParams::from_vector(&values[1..]).is_err());
+    assert!(EvalParams::zero().to_vector().iter().all(|&value| value == 0));
+}
+
+// The coefficients times the weights give back the evaluation from Red's
+// side, apart from the rounding of each term
+#[test]
+fn evaluation_is_linear_in_the_weights() {
+    let weights = EvalParams::default().to_vector();
+    for board in random_positions(300, 5) {
+        let linear: i64 = coefficients(&board)
+            .to_vector()
+            .iter()
+            .zip(&weights)
+            .map(|(&c, &w)| c as i64 * w as i64)
+            .sum();
+        let red_eval = if board.red_to_move { evaluate_position(&board) } else { -evaluate_position(&board) };
+        let difference = linear as f64 / MAX_PHASE as f64 - red_eval as f64;
+        assert!(difference.abs() <= 10.0, "{} off by {}", board.to_fen(), difference);
+    }
+}
+
+#[test]
+fn quiet_position_plays_out_captures() {
+    // Red's chariot can take the unprotected black chariot on a4
+    let board = Board::from_fen("2bakab2/9/9/9/9/r8/4P4/9/9/R1BAKAB2 w - - 0 1").unwrap();
+    let quiet = quiet_position(&board, EvalParams::defaults());
+    assert_eq!(quiet.to_fen().split(' ').next(), Some("2bakab2/9/9/9/9/R8/4P4/9/9/2BAKAB2"));
+}
+
+#[test]
+fn tuning_lowers_the_error() {
+    // Results that follow the material count, so more accurate piece values
+    // must predict them better
+    let mut set = TuningSet::new();
+    let params = EvalParams::default();
+    for board in random_positions(400, 6) {
+        let material = &chess_engine::evaluate_terms(&board)[0];
+        let result = match material.red - material.black {
+            diff if diff > 200 => 1.0,
+            diff if diff < -200 => 0.0,
+            _ => 0.5,
+        };
+        set.add(&board, result, &params);
+    }
+    assert!(set.len() > 300);
+
+    let k = set.best_k(&params);
+    let before = set.error(&params, k);
+    let mut tuner = Tuner::new(&set, &params, k, 1.0);
+    for _ in 0..30 {
+        tuner.step();
+    }
+    assert!(set.error(&tuner.params(), k) < before);
+}
@@ -0,0 +1,53 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// Feeds a CECP session to the engine binary and returns what it printed
+fn transcript(commands: &[&str]) -> Vec<String> {
+    let mut engine = Command::new(env!("CARGO_BIN_EXE_chess_engine"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = engine.stdin.take().unwrap();
+    for command in commands {
+        writeln!(stdin, "{}", command).unwrap();
+    }
+    drop(stdin);
+    let output = engine.wait_with_output().unwrap();
+    String::from_utf8(output.stdout).unwrap().lines().map(String::from).collect()
+}
+
+fn position(lines: &[String], line: &str) -> usize {
+    lines.iter().position(|l| l == line).unwrap_or_else(|| panic!("no {:?} in {:#?}", line, lines))
+}
+
+#[test]
+fn usermove_and_go_session() {
+    let lines = transcript(&[
+        "xboard",
+        "protover 2",
+        "new",
+        "variant xiangqi",
+        "sd 2",
+        "usermove h2e2",
+        "ping 1",
+        "usermove a0a9",
+        "ping 2",
+        "force",
+        "setboard 3k5/1R7/9/9/9/9/9/9/9/R4K3 w - - 0 1",
+        "go",
+        "quit",
+    ]);
+
+    assert!(lines[0].starts_with("feature ") && lines[0].ends_with("done=1"), "{:#?}", lines);
+    // the engine plays Black and answers the user's move before the ping
+    let reply = lines.iter().position(|l| l.starts_with("move ")).unwrap();
+    assert!(reply < position(&lines, "pong 1"));
+    // a0a9 is not legal for Black
+    assert!(position(&lines, "Illegal move: a0a9") < position(&lines, "pong 2"));
+    // after `go` the engine takes the side to move and mates
+    let mate = position(&lines, "move a0a9");
+    assert!(mate > position(&lines, "pong 2"));
+    assert_eq!(lines[mate + 1], "1-0 {Red wins}");
+}